@@ -13,6 +13,8 @@ pub struct Program {
     pub exports: Vec<Export>,
     /// rust structs
     pub structs: Vec<Struct>,
+    /// rust enums
+    pub enums: Vec<Enum>,
 }
 
 impl Program {
@@ -35,6 +37,8 @@ pub struct Export {
     pub rust_class: Option<Ident>,
     /// The name of the rust function/method on the rust source code
     pub rust_name: Ident,
+    /// Concrete types the type parameters of a generic function are instantiated with
+    pub generics: Vec<syn::Type>,
 }
 
 /// The type of a method
@@ -73,6 +77,10 @@ pub struct Function {
     pub arguments: Vec<syn::PatType>,
     /// The return type of the function, if provided
     pub ret: Option<syn::Type>,
+    /// Names of the positions of a tuple output, if provided
+    pub outputs: Vec<Ident>,
+    /// Doc comments of the function
+    pub docs: String,
 }
 
 /// Information about a Struct being exported
@@ -85,6 +93,24 @@ pub struct Struct {
     pub name: String,
     /// All the fields of this struct to export
     pub fields: Vec<StructField>,
+    /// How the fields of this struct are declared
+    pub style: StructStyle,
+    /// The type parameters of this struct
+    pub generics: syn::Generics,
+}
+
+/// How the fields of a struct are declared, which tells how serde serializes it
+#[cfg_attr(feature = "extra-traits", derive(Debug, PartialEq, Eq))]
+#[derive(Clone, Copy)]
+pub enum StructStyle {
+    /// Struct with named fields, serialized as a map
+    Named,
+    /// Tuple struct with several positional fields, serialized as an array
+    Tuple,
+    /// Tuple struct with a single positional field, serialized as this field
+    Newtype,
+    /// Struct without any field, serialized as a unit
+    Unit,
 }
 
 /// The field of a struct
@@ -99,15 +125,114 @@ pub struct StructField {
     pub struct_name: Ident,
     /// The type of this field
     pub ty: syn::Type,
+    /// Whether the fields of this field are serialized inline in the struct
+    pub flatten: bool,
+}
+
+/// Information about an Enum being exported
+#[cfg_attr(feature = "extra-traits", derive(Debug, PartialEq, Eq))]
+#[derive(Clone)]
+pub struct Enum {
+    /// The name of the enum in Rust code
+    pub rust_name: Ident,
+    /// The name of the enum for Holium
+    pub name: String,
+    /// All the variants of this enum
+    pub variants: Vec<Variant>,
+    /// The type parameters of this enum
+    pub generics: syn::Generics,
+}
+
+/// The variant of an enum
+#[cfg_attr(feature = "extra-traits", derive(Debug, PartialEq, Eq))]
+#[derive(Clone)]
+pub struct Variant {
+    /// The name of the variant in Rust code
+    pub rust_name: Ident,
+    /// The name of the variant in code
+    pub name: String,
+    /// The fields of the variant
+    pub fields: VariantFields,
+}
+
+/// The fields carried by an enum variant
+#[cfg_attr(feature = "extra-traits", derive(Debug, PartialEq, Eq))]
+#[derive(Clone)]
+pub enum VariantFields {
+    /// Struct variant, with named fields
+    Named(Vec<StructField>),
+    /// Tuple or newtype variant, with the types of its positional fields
+    Unnamed(Vec<syn::Type>),
+    /// Unit variant, carrying no data
+    Unit,
+}
+
+impl Function {
+    /// Returns true if the function returns a `Result`, in which case its `Err` variant is handed
+    /// to the host as a failure instead of being part of the output
+    pub(crate) fn returns_result(&self) -> bool {
+        self.result_types().is_some()
+    }
+
+    /// Types of the `Ok` and `Err` variants of a returned `Result`. Only `Result`,
+    /// `std::result::Result` and `core::result::Result` with two type arguments are recognized, other
+    /// types named `Result` such as `io::Result<T>` being plain outputs.
+    fn result_types(&self) -> Option<(&syn::Type, &syn::Type)> {
+        let path = match &self.ret {
+            Some(syn::Type::Path(syn::TypePath { qself: None, path })) => path,
+            _ => return None,
+        };
+        let idents: Vec<String> = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        let is_result = match idents.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["Result"] => path.leading_colon.is_none(),
+            ["std", "result", "Result"] | ["core", "result", "Result"] => true,
+            _ => false,
+        };
+        if !is_result {
+            return None;
+        }
+
+        match &path.segments.last()?.arguments {
+            syn::PathArguments::AngleBracketed(arguments) => {
+                match arguments.args.iter().collect::<Vec<_>>()[..] {
+                    [syn::GenericArgument::Type(ok), syn::GenericArgument::Type(err)] => {
+                        Some((ok, err))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Type of the output of the function, which is the `Ok` variant of a returned `Result`. Is
+    /// `None` when the function returns nothing.
+    pub fn output_type(&self) -> Option<&syn::Type> {
+        match self.result_types() {
+            Some((ok, _)) => Some(ok),
+            None => self.ret.as_ref(),
+        }
+    }
 }
 
 impl Export {
     /// Generate unique function name for our exported Rust function. For a function named "main" the
-    /// resulting name will be "__holium_bindgen_generated_main"
+    /// resulting name will be "__holium_bindgen_generated_main". Exported names that are not
+    /// identifiers are mangled, see [`mangle`].
     pub(crate) fn rust_symbol(&self) -> Ident {
-        let mut generated_name = String::from("__holium_bindgen_generated");
-        generated_name.push_str("_");
-        generated_name.push_str(&self.function.name.to_string());
+        let generated_name = format!("__holium_bindgen_generated_{}", mangle(&self.function.name));
+        Ident::new(&generated_name, Span::call_site())
+    }
+
+    /// Name of the hidden function returning the key trees of the inputs and of the output of our
+    /// exported Rust function. For a function named "main" the resulting name will be
+    /// "__holium_bindgen_key_trees_main".
+    pub(crate) fn key_trees_symbol(&self) -> Ident {
+        let generated_name = format!("__holium_bindgen_key_trees_{}", mangle(&self.function.name));
         Ident::new(&generated_name, Span::call_site())
     }
 
@@ -118,3 +243,28 @@ impl Export {
         self.function.name.to_string()
     }
 }
+
+/// Mangle an exported name into a suffix for generated identifiers, distinct names always giving
+/// distinct suffixes. Names that are ASCII identifiers are kept as they are. Other names start with
+/// a `0`, which no identifier starts with, followed by the name in which every character that is
+/// not an ASCII alphanumeric, underscores included, is replaced by its hexadecimal code point
+/// between underscores: "sum-of-values" gives "0sum_2d_of_2d_values".
+fn mangle(name: &str) -> String {
+    let mut chars = name.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        return String::from(name);
+    }
+
+    let mut mangled = String::from("0");
+    for c in name.chars() {
+        match c.is_ascii_alphanumeric() {
+            true => mangled.push(c),
+            false => mangled.push_str(&format!("_{:x}_", u32::from(c))),
+        }
+    }
+    mangled
+}
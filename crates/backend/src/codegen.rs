@@ -4,7 +4,8 @@ use crate::ast;
 use crate::Diagnostic;
 use proc_macro2::{Ident, Span, TokenStream};
 use quote::{quote, ToTokens};
-use syn;
+use syn::ext::IdentExt;
+use syn::visit_mut::VisitMut;
 
 /// A trait for converting AST structs into Tokens and adding them to a TokenStream,
 /// or providing a diagnostic if conversion fails.
@@ -36,6 +37,11 @@ impl TryToTokens for ast::Program {
             s.to_tokens(into);
         }
 
+        // Handling tagged enums
+        for e in self.enums.iter() {
+            e.to_tokens(into);
+        }
+
         Diagnostic::from_vec(errors)?;
 
         Ok(())
@@ -54,41 +60,146 @@ impl ToTokens for ast::Struct {
         })
             .to_token_stream();
 
-        // For each field of our structure add a new children node
+        // Occurrences of the structure in its own fields are cut from its key node
+        let mut cut_recursion = CutRecursion::new(&self.rust_name, &self.generics);
+        let fields = cut_recursion.fields(&self.fields);
+
+        // Key node of the structure, mirroring the way serde serializes it
+        let node = match (self.style, fields.first()) {
+            // Newtypes are serialized as their inner value
+            (ast::StructStyle::Newtype, Some(field)) => {
+                let field_type = &field.ty;
+                quote! {
+                    <#field_type as holium_rs_sdk::internal::key_tree::GenerateNode>::KEY_NODE
+                }
+            }
+            // Units and newtypes whose field is skipped are serialized as a unit
+            (ast::StructStyle::Newtype, None) | (ast::StructStyle::Unit, _) => quote! {
+                holium_rs_sdk::internal::key_tree::StaticNode::DEFAULT
+            },
+            // For each field of our structure add a new children node, named after its position for
+            // tuple structs
+            (style, _) => fields_key_node(&fields, style),
+        };
+
+        let node = cut_recursion.label(node);
+        let (generics, try_from_generics) = impl_generics(&self.generics, cut_recursion.found);
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let (try_from_impl_generics, _, try_from_where_clause) = try_from_generics.split_for_impl();
+        let recursive_impl = cut_recursion.recursive_impl(&generics);
+
+        // Generating conversion from data_tree::Node to structure and implement key_tree::GenerateNode
+        // trait
+        (quote! {
+            impl #impl_generics holium_rs_sdk::internal::key_tree::GenerateNode for #name #ty_generics #where_clause {
+                const KEY_NODE: holium_rs_sdk::internal::key_tree::StaticNode = #node;
+            }
+
+            #recursive_impl
+
+            impl #try_from_impl_generics std::convert::TryFrom<holium_rs_sdk::internal::data_tree::Node> for #name #ty_generics #try_from_where_clause {
+                type Error = holium_rs_sdk::internal::error::Error;
+
+                fn try_from(data_tree: holium_rs_sdk::internal::data_tree::Node) -> std::result::Result<Self, Self::Error> {
+                    data_tree.deserialize::<Self>()
+                }
+            }
+        })
+        .to_tokens(into);
+    }
+}
+
+impl ToTokens for ast::Enum {
+    fn to_tokens(&self, into: &mut TokenStream) {
+        let name = &self.rust_name;
+
+        // Add derive for serialize & deserialize. Enums are adjacently tagged so that the name of
+        // their variant is kept in data trees, tag and content keys have to match the ones expected
+        // by `data_tree::Node::assign_keys`
+        *into = (quote! {
+            #[derive(holium_rs_sdk::internal::serde::Serialize, holium_rs_sdk::internal::serde::Deserialize)]
+            #[serde( crate = "holium_rs_sdk::internal::serde", tag = "tag", content = "content")]
+            #into
+        })
+        .to_token_stream();
+
+        // Occurrences of the enum in the content of its variants are cut from its key node
+        let mut cut_recursion = CutRecursion::new(&self.rust_name, &self.generics);
+
+        // For each variant of our enum add a new children node holding the keys of its content
         let mut generate_node_children: Vec<TokenStream> = vec![];
 
-        for field in self.fields.iter() {
-            let field_name = field.name.to_string();
-            let field_type = &field.ty;
+        for variant in self.variants.iter() {
+            let variant_name = variant.name.to_string();
 
-            generate_node_children.push(quote! {
-                holium_rs_sdk::internal::key_tree::Node {
-                    value: Some(#field_name),
-                    children: <#field_type>::generate_node().children
+            generate_node_children.push(match &variant.fields {
+                ast::VariantFields::Named(fields) => {
+                    let fields = cut_recursion.fields(fields);
+                    let fields_node = fields_key_node(&fields, ast::StructStyle::Named);
+                    quote! {
+                        holium_rs_sdk::internal::key_tree::StaticNode {
+                            value: Some(#variant_name),
+                            ..#fields_node
+                        }
+                    }
+                }
+                // Newtype variants are serialized as their inner value
+                ast::VariantFields::Unnamed(types) if types.len() == 1 => {
+                    let ty = cut_recursion.ty(&types[0]);
+                    quote! {
+                        holium_rs_sdk::internal::key_tree::StaticNode {
+                            value: Some(#variant_name),
+                            ..<#ty as holium_rs_sdk::internal::key_tree::GenerateNode>::KEY_NODE
+                        }
+                    }
+                }
+                // Tuple variants are serialized as a tuple of their fields
+                ast::VariantFields::Unnamed(types) => {
+                    let types = types.iter().map(|ty| cut_recursion.ty(ty));
+                    quote! {
+                        holium_rs_sdk::internal::key_tree::StaticNode {
+                            value: Some(#variant_name),
+                            ..<(#(#types),*) as holium_rs_sdk::internal::key_tree::GenerateNode>::KEY_NODE
+                        }
+                    }
                 }
+                ast::VariantFields::Unit => quote! {
+                    holium_rs_sdk::internal::key_tree::StaticNode {
+                        value: Some(#variant_name),
+                        ..holium_rs_sdk::internal::key_tree::StaticNode::DEFAULT
+                    }
+                },
             });
         }
 
-        // Generating conversion from data_tree::Node to structure and implement key_tree::GenerateNode
+        let node = cut_recursion.label(quote! {
+            holium_rs_sdk::internal::key_tree::StaticNode {
+                children: &[
+                    #(#generate_node_children),*
+                ],
+                shape: holium_rs_sdk::internal::key_tree::Shape::Enum,
+                ..holium_rs_sdk::internal::key_tree::StaticNode::DEFAULT
+            }
+        });
+        let (generics, try_from_generics) = impl_generics(&self.generics, cut_recursion.found);
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let (try_from_impl_generics, _, try_from_where_clause) = try_from_generics.split_for_impl();
+        let recursive_impl = cut_recursion.recursive_impl(&generics);
+
+        // Generating conversion from data_tree::Node to enum and implement key_tree::GenerateNode
         // trait
         (quote! {
-            impl holium_rs_sdk::internal::key_tree::GenerateNode for #name {
-                fn generate_node() -> holium_rs_sdk::internal::key_tree::Node {
-                    holium_rs_sdk::internal::key_tree::Node {
-                        value: None,
-                        children: vec![
-                            #(#generate_node_children),*
-                        ],
-                    }
-                }
+            impl #impl_generics holium_rs_sdk::internal::key_tree::GenerateNode for #name #ty_generics #where_clause {
+                const KEY_NODE: holium_rs_sdk::internal::key_tree::StaticNode = #node;
             }
 
-            impl From<holium_rs_sdk::internal::data_tree::Node> for #name {
-                fn from(data_tree: holium_rs_sdk::internal::data_tree::Node) -> Self {
-                    let key_node = <#name>::generate_node();
-                    let cbor = data_tree.assign_keys(&key_node);
-                    let cbor_bytes: Vec<u8> = internal::serde_cbor::to_vec(&cbor).unwrap();
-                    holium_rs_sdk::internal::serde_cbor::from_slice(&cbor_bytes).unwrap()
+            #recursive_impl
+
+            impl #try_from_impl_generics std::convert::TryFrom<holium_rs_sdk::internal::data_tree::Node> for #name #ty_generics #try_from_where_clause {
+                type Error = holium_rs_sdk::internal::error::Error;
+
+                fn try_from(data_tree: holium_rs_sdk::internal::data_tree::Node) -> std::result::Result<Self, Self::Error> {
+                    data_tree.deserialize::<Self>()
                 }
             }
         })
@@ -96,23 +207,377 @@ impl ToTokens for ast::Struct {
     }
 }
 
+/// Generate the key node of a structure or of a struct variant from its fields. The key node of a
+/// flattened field has no key, and makes the structure be read as a map of its fields, the way
+/// serde serializes it.
+fn fields_key_node(fields: &[ast::StructField], style: ast::StructStyle) -> TokenStream {
+    let key_nodes = fields.iter().map(|field| {
+        let field_type = &field.ty;
+        let field_name = match field.flatten {
+            true => quote! { None },
+            false => {
+                let field_name = field.name.to_string();
+                quote! { Some(#field_name) }
+            }
+        };
+
+        quote! {
+            holium_rs_sdk::internal::key_tree::StaticNode {
+                value: #field_name,
+                ..<#field_type as holium_rs_sdk::internal::key_tree::GenerateNode>::KEY_NODE
+            }
+        }
+    });
+    let shape = match style {
+        ast::StructStyle::Tuple => quote! { Tuple },
+        _ if fields.iter().any(|field| field.flatten) => quote! { Flattened },
+        _ => quote! { Plain },
+    };
+
+    quote! {
+        holium_rs_sdk::internal::key_tree::StaticNode {
+            children: &[#(#key_nodes),*],
+            shape: holium_rs_sdk::internal::key_tree::Shape::#shape,
+            ..holium_rs_sdk::internal::key_tree::StaticNode::DEFAULT
+        }
+    }
+}
+
+/// Replaces the occurrences of a struct or enum in its own fields by
+/// `key_tree::Recursion<Self>`, whose key node references the type instead of holding its key
+/// node. Occurrences are `Self`, or the name of the type followed by its own type parameters.
+struct CutRecursion<'a> {
+    name: &'a Ident,
+    generics: &'a syn::Generics,
+    /// Whether an occurrence of the type has been found
+    found: bool,
+}
+
+impl<'a> CutRecursion<'a> {
+    fn new(name: &'a Ident, generics: &'a syn::Generics) -> Self {
+        CutRecursion {
+            name,
+            generics,
+            found: false,
+        }
+    }
+
+    fn ty(&mut self, ty: &syn::Type) -> syn::Type {
+        let mut ty = ty.clone();
+        self.visit_type_mut(&mut ty);
+        ty
+    }
+
+    fn fields(&mut self, fields: &[ast::StructField]) -> Vec<ast::StructField> {
+        fields
+            .iter()
+            .map(|field| ast::StructField {
+                ty: self.ty(&field.ty),
+                ..field.clone()
+            })
+            .collect()
+    }
+
+    fn is_occurrence(&self, path: &syn::Path) -> bool {
+        let segment = match (path.leading_colon, path.segments.len()) {
+            (None, 1) => &path.segments[0],
+            _ => return false,
+        };
+        if segment.ident == "Self" {
+            return segment.arguments.is_empty();
+        }
+        if segment.ident != *self.name {
+            return false;
+        }
+
+        let params = self.generics.params.iter().map(|param| match param {
+            syn::GenericParam::Type(param) => param.ident.to_string(),
+            syn::GenericParam::Lifetime(param) => param.lifetime.to_string(),
+            syn::GenericParam::Const(param) => param.ident.to_string(),
+        });
+        match &segment.arguments {
+            syn::PathArguments::None => self.generics.params.is_empty(),
+            syn::PathArguments::AngleBracketed(arguments) => arguments
+                .args
+                .iter()
+                .map(|argument| quote! { #argument }.to_string())
+                .eq(params),
+            syn::PathArguments::Parenthesized(_) => false,
+        }
+    }
+
+    /// Label the key node of the type with its name if it is recursive
+    fn label(&self, node: TokenStream) -> TokenStream {
+        match self.found {
+            true => quote! {
+                holium_rs_sdk::internal::key_tree::StaticNode {
+                    type_name: Some(<Self as holium_rs_sdk::internal::key_tree::Recursive>::TYPE_NAME),
+                    ..#node
+                }
+            },
+            false => node,
+        }
+    }
+
+    /// Implementation of `key_tree::Recursive` for the type if it is recursive, naming it after
+    /// its path
+    fn recursive_impl(&self, generics: &syn::Generics) -> TokenStream {
+        if !self.found {
+            return quote! {};
+        }
+
+        let name = self.name;
+        let type_name = name.to_string();
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        quote! {
+            impl #impl_generics holium_rs_sdk::internal::key_tree::Recursive for #name #ty_generics #where_clause {
+                const TYPE_NAME: &'static str = concat!(module_path!(), "::", #type_name);
+            }
+        }
+    }
+}
+
+impl VisitMut for CutRecursion<'_> {
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        match ty {
+            syn::Type::Path(syn::TypePath { qself: None, path }) if self.is_occurrence(path) => {
+                *ty = syn::parse_quote!(holium_rs_sdk::internal::key_tree::Recursion<Self>);
+                self.found = true;
+            }
+            _ => syn::visit_mut::visit_type_mut(self, ty),
+        }
+    }
+}
+
+/// Generics of the trait implementations of a generic struct or enum. Type parameters are bound to
+/// `GenerateNode` so that the key tree of the type can be generated, and to `'static` if the type
+/// is recursive, as its key tree then references it. The type itself has to be deserializable to
+/// be converted from a data tree.
+fn impl_generics(generics: &syn::Generics, recursive: bool) -> (syn::Generics, syn::Generics) {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(
+            holium_rs_sdk::internal::key_tree::GenerateNode
+        ));
+        if recursive {
+            param.bounds.push(syn::parse_quote!('static));
+        }
+    }
+
+    let mut try_from_generics = generics.clone();
+    if try_from_generics.type_params().next().is_some() {
+        try_from_generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote!(Self: holium_rs_sdk::internal::serde::de::DeserializeOwned));
+    }
+
+    (generics, try_from_generics)
+}
+
+/// Attribute to serialize the arrays held by a field of the given type with our own helpers, as
+/// serde cannot serialize arrays of more than 32 elements by itself. Is `None` when the field holds
+/// no array, see `holium_rs_sdk::internal::array`.
+pub fn array_serde_attribute(ty: &syn::Type) -> Option<syn::Attribute> {
+    let with = match ty {
+        syn::Type::Array(array) if serialize_as_type(&array.elem).is_none() => {
+            String::from("holium_rs_sdk::internal::array")
+        }
+        _ => format!(
+            "holium_rs_sdk::internal::array::As::<{}>",
+            serialize_as_type(ty)?
+        ),
+    };
+    Some(syn::parse_quote! {
+        #[serde(with = #with)]
+    })
+}
+
+/// Type with which a value holding arrays is serialized through
+/// `holium_rs_sdk::internal::array::SerializeAs`, mirroring its own type with other types replaced
+/// by `Same`. Arrays are only looked for in tuples and in standard containers, so that `None` is
+/// returned when the type holds no array to handle.
+pub(crate) fn serialize_as_type(ty: &syn::Type) -> Option<TokenStream> {
+    let same = quote! { holium_rs_sdk::internal::array::Same };
+    let or_same = |ty: Option<TokenStream>| ty.unwrap_or_else(|| same.clone());
+    match ty {
+        syn::Type::Array(array) => {
+            let elem = or_same(serialize_as_type(&array.elem));
+            let len = &array.len;
+            Some(quote! { [#elem; #len] })
+        }
+        syn::Type::Paren(paren) => serialize_as_type(&paren.elem),
+        syn::Type::Group(group) => serialize_as_type(&group.elem),
+        syn::Type::Tuple(tuple) => {
+            let elems: Vec<Option<TokenStream>> =
+                tuple.elems.iter().map(serialize_as_type).collect();
+            if elems.iter().all(Option::is_none) {
+                return None;
+            }
+            let elems = elems.into_iter().map(or_same);
+            Some(quote! { (#(#elems,)*) })
+        }
+        syn::Type::Path(syn::TypePath { qself: None, path }) => {
+            let container = std_container(path)?;
+            let arguments = match &path.segments.last()?.arguments {
+                syn::PathArguments::AngleBracketed(arguments) => arguments,
+                _ => return None,
+            };
+            let mut types = Vec::new();
+            for argument in arguments.args.iter() {
+                match argument {
+                    syn::GenericArgument::Type(ty) => types.push(serialize_as_type(ty)),
+                    _ => return None,
+                }
+            }
+            if types.len() != container.arity || types.iter().all(Option::is_none) {
+                return None;
+            }
+            let path: TokenStream = container.path.parse().unwrap();
+            let types = types.into_iter().map(or_same);
+            Some(quote! { #path<#(#types),*> })
+        }
+        _ => None,
+    }
+}
+
+/// Standard container in which arrays are looked for
+struct StdContainer {
+    /// Name of the container
+    name: &'static str,
+    /// Modules from which the container can be named
+    modules: &'static [&'static str],
+    /// Path to the container, from the generated code
+    path: &'static str,
+    /// Number of type parameters of the container
+    arity: usize,
+}
+
+const STD_CONTAINERS: &[StdContainer] = &[
+    StdContainer {
+        name: "Vec",
+        modules: &["std::vec", "alloc::vec"],
+        path: "std::vec::Vec",
+        arity: 1,
+    },
+    StdContainer {
+        name: "VecDeque",
+        modules: &["std::collections", "alloc::collections"],
+        path: "std::collections::VecDeque",
+        arity: 1,
+    },
+    StdContainer {
+        name: "Option",
+        modules: &["std::option", "core::option"],
+        path: "std::option::Option",
+        arity: 1,
+    },
+    StdContainer {
+        name: "Box",
+        modules: &["std::boxed", "alloc::boxed"],
+        path: "std::boxed::Box",
+        arity: 1,
+    },
+    StdContainer {
+        name: "BTreeMap",
+        modules: &["std::collections", "alloc::collections"],
+        path: "std::collections::BTreeMap",
+        arity: 2,
+    },
+    StdContainer {
+        name: "HashMap",
+        modules: &["std::collections"],
+        path: "std::collections::HashMap",
+        arity: 2,
+    },
+];
+
+/// Standard container a path points to, either by its name or by its full path
+fn std_container(path: &syn::Path) -> Option<&'static StdContainer> {
+    let idents: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    let (name, modules) = idents.split_last()?;
+    let modules = modules.join("::");
+    STD_CONTAINERS.iter().find(|container| {
+        container.name == name
+            && match modules.is_empty() {
+                true => path.leading_colon.is_none(),
+                false => container.modules.contains(&modules.as_str()),
+            }
+    })
+}
+
+/// Name of a type as written in the manifest, without the spacing added between its tokens
+fn type_name(ty: &syn::Type) -> String {
+    let tokens = quote! { #ty }.to_string();
+    let chars: Vec<char> = tokens.chars().collect();
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+
+    let mut name = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        match c {
+            ' ' if i > 0 && i + 1 < chars.len() => {
+                if (is_word(&chars[i - 1]) && is_word(&chars[i + 1]))
+                    || chars[i - 1] == ','
+                    || chars[i - 1] == ';'
+                {
+                    name.push(' ');
+                }
+            }
+            ' ' => {}
+            c => name.push(*c),
+        }
+    }
+    name
+}
+
 impl TryToTokens for ast::Export {
     fn try_to_tokens(self: &ast::Export, into: &mut TokenStream) -> Result<(), Diagnostic> {
         let mut input_payload_fields: Vec<TokenStream> = vec![];
         let mut input_payload_node_children: Vec<TokenStream> = vec![];
         let mut converted_args: Vec<TokenStream> = vec![];
+        // The input payload is only bound mutably when lent to a `&mut` argument
+        let mut input_binding = quote! { input };
 
         let name = &self.rust_name;
-        let receiver = quote! { #name };
+        // Generic functions are called with the concrete types of their instantiation
+        let generics = &self.generics;
+        let receiver = match generics.is_empty() {
+            true => quote! { #name },
+            false => quote! { #name::<#(#generics),*> },
+        };
 
         let exported_name = &self.export_name();
         let holium_func_name = &self.rust_symbol();
+        let key_trees_func_name = &self.key_trees_symbol();
 
         // First, generating inputs elements : input payload struct & function arguments
+        let mut keys: Vec<String> = vec![];
+        let mut manifest_parameters: Vec<TokenStream> = vec![];
         for (i, arg) in self.function.arguments.iter().enumerate() {
             let field = format!("arg{}", i);
             let field_ident = Ident::new(&field, Span::call_site());
-            let input_ident = Ident::new(&format!("input"), Span::call_site());
+            // Inputs are keyed by the identifiers of their parameters, or by their position when
+            // bound to another pattern
+            let key = match &*arg.pat {
+                syn::Pat::Ident(syn::PatIdent { ident, .. }) => ident.unraw().to_string(),
+                _ => field.clone(),
+            };
+            if keys.contains(&key) {
+                bail_span!(arg.pat, "duplicate input key `{}`", key);
+            }
+            keys.push(key.clone());
+            let manifest_type = type_name(&arg.ty);
+            manifest_parameters.push(quote! {
+                holium_rs_sdk::internal::manifest::Parameter {
+                    name: #key,
+                    ty: #manifest_type,
+                }
+            });
+            let input_ident = Ident::new("input", Span::call_site());
             let ty = &arg.ty;
 
             match &*arg.ty {
@@ -122,28 +587,35 @@ impl TryToTokens for ast::Export {
                     elem,
                     ..
                 }) => {
+                    let serde_attribute = array_serde_attribute(elem);
                     input_payload_fields.push(quote! {
+                        #serde_attribute
+                        #[serde(rename = #key)]
                         #field_ident: #elem
                     });
                     input_payload_node_children.push(quote! {
-                        holium_rs_sdk::internal::key_tree::Node {
-                            value: Some(#field),
-                            children: <#elem>::generate_node().children
+                        holium_rs_sdk::internal::key_tree::StaticNode {
+                            value: Some(#key),
+                            ..<#elem as holium_rs_sdk::internal::key_tree::GenerateNode>::KEY_NODE
                         }
                     });
                     converted_args.push(quote! {
                         &mut #input_ident.#field_ident
                     });
+                    input_binding = quote! { mut input };
                 }
                 // If argument type is non-mutable reference
                 syn::Type::Reference(syn::TypeReference { elem, .. }) => {
+                    let serde_attribute = array_serde_attribute(elem);
                     input_payload_fields.push(quote! {
+                        #serde_attribute
+                        #[serde(rename = #key)]
                         #field_ident: #elem
                     });
                     input_payload_node_children.push(quote! {
-                        holium_rs_sdk::internal::key_tree::Node {
-                            value: Some(#field),
-                            children: <#elem>::generate_node().children
+                        holium_rs_sdk::internal::key_tree::StaticNode {
+                            value: Some(#key),
+                            ..<#elem as holium_rs_sdk::internal::key_tree::GenerateNode>::KEY_NODE
                         }
                     });
                     // If argument type is non-mutable reference but a &str no need to add &
@@ -159,13 +631,16 @@ impl TryToTokens for ast::Export {
                 }
                 // For all other types
                 _ => {
+                    let serde_attribute = array_serde_attribute(ty);
                     input_payload_fields.push(quote! {
+                        #serde_attribute
+                        #[serde(rename = #key)]
                         #field_ident: #ty
                     });
                     input_payload_node_children.push(quote! {
-                        holium_rs_sdk::internal::key_tree::Node {
-                            value: Some(#field),
-                            children: <#ty>::generate_node().children
+                        holium_rs_sdk::internal::key_tree::StaticNode {
+                            value: Some(#key),
+                            ..<#ty as holium_rs_sdk::internal::key_tree::GenerateNode>::KEY_NODE
                         }
                     });
                     converted_args.push(quote! {
@@ -175,14 +650,154 @@ impl TryToTokens for ast::Export {
             }
         }
 
+        // If the function returns a `Result`, only its `Ok` variant is part of the output while its
+        // `Err` variant is handed to the host as a failure
+        let handle_failure = if self.function.returns_result() {
+            quote! {
+                let output = match output {
+                    Ok(output) => output,
+                    Err(e) => {
+                        use holium_rs_sdk::internal::error::{OpaqueFailure, SerializableFailure};
+                        return Ok(holium_rs_sdk::internal::memory::Output::failure(
+                            holium_rs_sdk::internal::error::Failure {
+                                message: e.to_string(),
+                                payload: (&holium_rs_sdk::internal::error::FailurePayload(&e)).payload(),
+                            }
+                        ));
+                    }
+                };
+            }
+        } else {
+            quote! {}
+        };
+
+        // Serde cannot serialize arrays of more than 32 elements by itself
+        let wrap_array = match self.function.output_type().and_then(serialize_as_type) {
+            Some(serialize_as) => quote! {
+                let output = holium_rs_sdk::internal::array::SerializedAs::<_, #serialize_as>::new(output);
+            },
+            None => quote! {},
+        };
+
+        // Named outputs are the positions of a returned tuple, serialized as they are. Other outputs
+        // are the single unnamed element of a tuple.
+        let output_type = match self.function.output_type() {
+            Some(ty) => quote! { #ty },
+            None => quote! { () },
+        };
+        let (output_node, serialized_output, manifest_outputs) = match (
+            self.function.output_type(),
+            self.function.outputs.is_empty(),
+        ) {
+            (Some(syn::Type::Tuple(tuple)), false) => {
+                let names = self
+                    .function
+                    .outputs
+                    .iter()
+                    .map(|name| name.unraw().to_string());
+                let elems = tuple.elems.iter();
+                let manifest_outputs =
+                    self.function
+                        .outputs
+                        .iter()
+                        .zip(tuple.elems.iter())
+                        .map(|(name, ty)| {
+                            let name = name.unraw().to_string();
+                            let ty = type_name(ty);
+                            quote! {
+                                holium_rs_sdk::internal::manifest::Parameter {
+                                    name: #name,
+                                    ty: #ty,
+                                }
+                            }
+                        });
+                (
+                    quote! {
+                        holium_rs_sdk::internal::key_tree::StaticNode {
+                            children: &[
+                                #(holium_rs_sdk::internal::key_tree::StaticNode {
+                                    value: Some(#names),
+                                    ..<#elems as holium_rs_sdk::internal::key_tree::GenerateNode>::KEY_NODE
+                                }),*
+                            ],
+                            ..holium_rs_sdk::internal::key_tree::StaticNode::DEFAULT
+                        }
+                    },
+                    quote! { &output },
+                    quote! { #(#manifest_outputs),* },
+                )
+            }
+            _ => (
+                quote! {
+                    holium_rs_sdk::internal::key_tree::StaticNode {
+                        children: &[holium_rs_sdk::internal::key_tree::StaticNode {
+                            value: Some("0"),
+                            ..<#output_type as holium_rs_sdk::internal::key_tree::GenerateNode>::KEY_NODE
+                        }],
+                        shape: holium_rs_sdk::internal::key_tree::Shape::Tuple,
+                        ..holium_rs_sdk::internal::key_tree::StaticNode::DEFAULT
+                    }
+                },
+                quote! { &vec![output] },
+                {
+                    let ty = match self.function.output_type() {
+                        Some(ty) => type_name(ty),
+                        None => String::from("()"),
+                    };
+                    quote! {
+                        holium_rs_sdk::internal::manifest::Parameter {
+                            name: "0",
+                            ty: #ty,
+                        }
+                    }
+                },
+            ),
+        };
+
+        // Manifest entry of the transformation, encoded at compile time in a custom section
+        let docs = &self.function.docs;
+        let manifest = quote! {
+            const _: () = {
+                const ENTRY: holium_rs_sdk::internal::manifest::Entry = holium_rs_sdk::internal::manifest::Entry {
+                    name: #exported_name,
+                    docs: #docs,
+                    parameters: &[#(#manifest_parameters),*],
+                    outputs: &[#manifest_outputs],
+                    key_trees: *#key_trees_func_name(),
+                };
+
+                #[cfg_attr(target_arch = "wasm32", link_section = "__holium_manifest")]
+                #[used]
+                static MANIFEST: [u8; ENTRY.encoded_len()] = ENTRY.encode();
+            };
+        };
+
         (quote! {
+            /// Key trees of the input payload and of the output of the transformation
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            pub const fn #key_trees_func_name() -> &'static (holium_rs_sdk::internal::key_tree::StaticNode, holium_rs_sdk::internal::key_tree::StaticNode) {
+                const KEY_TREES: (holium_rs_sdk::internal::key_tree::StaticNode, holium_rs_sdk::internal::key_tree::StaticNode) = (
+                    holium_rs_sdk::internal::key_tree::StaticNode {
+                        children: &[
+                            #(#input_payload_node_children),*
+                        ],
+                        ..holium_rs_sdk::internal::key_tree::StaticNode::DEFAULT
+                    },
+                    #output_node,
+                );
+                &KEY_TREES
+            }
+
+            #manifest
+
             #[allow(non_snake_case)]
             #[cfg_attr(
                 all(target_arch = "wasm32"),
                 export_name = #exported_name,
             )]
             #[allow(clippy::all)]
-            pub extern "C" fn #holium_func_name(ptr: *mut u8, len: usize) -> holium_rs_sdk::internal::memory::Slice {
+            pub extern "C" fn #holium_func_name(ptr: *mut u8, len: usize) -> holium_rs_sdk::internal::memory::Output {
                 #[derive(holium_rs_sdk::internal::serde::Serialize, holium_rs_sdk::internal::serde::Deserialize)]
                 #[serde( crate = "holium_rs_sdk::internal::serde")]
                 struct InputPayload {
@@ -190,41 +805,41 @@ impl TryToTokens for ast::Export {
                 }
 
                 impl holium_rs_sdk::internal::key_tree::GenerateNode for InputPayload {
-                    fn generate_node() -> holium_rs_sdk::internal::key_tree::Node {
-                        holium_rs_sdk::internal::key_tree::Node {
-                            value: None,
-                            children: vec![
-                                #(#input_payload_node_children),*
-                            ]
-                        }
-                    }
+                    const KEY_NODE: holium_rs_sdk::internal::key_tree::StaticNode = #key_trees_func_name().0;
                 }
 
-                impl From<holium_rs_sdk::internal::data_tree::Node> for InputPayload {
-                    fn from(data_tree: holium_rs_sdk::internal::data_tree::Node) -> Self {
-                        let key_node = <InputPayload>::generate_node();
-                        let cbor = data_tree.assign_keys(&key_node);
-                        let cbor_bytes: Vec<u8> = internal::serde_cbor::to_vec(&cbor).unwrap();
-                        holium_rs_sdk::internal::serde_cbor::from_slice(&cbor_bytes).unwrap()
+                impl std::convert::TryFrom<holium_rs_sdk::internal::data_tree::Node> for InputPayload {
+                    type Error = holium_rs_sdk::internal::error::Error;
+
+                    fn try_from(data_tree: holium_rs_sdk::internal::data_tree::Node) -> std::result::Result<Self, Self::Error> {
+                        data_tree.deserialize::<InputPayload>()
                     }
                 }
 
-                let payload_u8: &[u8] = unsafe { std::slice::from_raw_parts(ptr, len) };
-                let data_node: holium_rs_sdk::internal::data_tree::Node = holium_rs_sdk::internal::serde_cbor::from_slice(payload_u8).unwrap();
+                let run = || -> std::result::Result<holium_rs_sdk::internal::memory::Output, holium_rs_sdk::internal::error::Error> {
+                    let payload_u8: &[u8] = unsafe { std::slice::from_raw_parts(ptr, len) };
+                    let data_node: holium_rs_sdk::internal::data_tree::Node = holium_rs_sdk::internal::serde_cbor::from_slice(payload_u8)
+                        .map_err(|e| holium_rs_sdk::internal::error::Error::PayloadDecode(e.to_string()))?;
 
-                let input: InputPayload = data_node.into();
+                    let #input_binding = <InputPayload as std::convert::TryFrom<_>>::try_from(data_node)?;
 
-                let output = #receiver(#(#converted_args),*);
+                    let output = holium_rs_sdk::internal::panic::catch_unwind(#exported_name, || {
+                        #receiver(#(#converted_args),*)
+                    })?;
 
-                let output_cbor = holium_rs_sdk::internal::serde_cbor::value::to_value(vec![output]).unwrap();
+                    #handle_failure
 
-                let output_node = holium_rs_sdk::internal::data_tree::Node::new(output_cbor).unwrap();
-                let output_node_u8 = holium_rs_sdk::internal::serde_cbor::to_vec(&output_node).unwrap();
+                    #wrap_array
 
-                holium_rs_sdk::internal::memory::Slice {
-                    ptr: output_node_u8.as_ptr() as u32,
-                    len: output_node_u8.len() as u32
-                }
+                    let output_node = holium_rs_sdk::internal::data_tree::Node::from_serializable(#serialized_output)
+                        .map_err(|e| holium_rs_sdk::internal::error::Error::OutputEncode(e.to_string()))?;
+                    let output_node_u8 = output_node.encode()
+                        .map_err(|e| holium_rs_sdk::internal::error::Error::OutputEncode(e.to_string()))?;
+
+                    Ok(holium_rs_sdk::internal::memory::Output::success(output_node_u8))
+                };
+
+                run().unwrap_or_else(holium_rs_sdk::internal::memory::Output::error)
             }
         })
             .to_tokens(into);
@@ -1,6 +1,6 @@
 //! Backend crate for the Holium Rust SDK procedural macro.
 
-pub use crate::codegen::TryToTokens;
+pub use crate::codegen::{array_serde_attribute, TryToTokens};
 pub use crate::error::Diagnostic;
 
 #[macro_use]
@@ -0,0 +1,113 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Target transformations are compiled for
+const TARGET: &str = "wasm32-unknown-unknown";
+
+#[derive(Args, Default)]
+pub struct BuildArgs {
+    /// Build with the dev profile instead of the release one
+    #[arg(long, conflicts_with = "profile")]
+    debug: bool,
+    /// Build with the given profile
+    #[arg(long)]
+    profile: Option<String>,
+    /// Package to build
+    #[arg(short, long)]
+    package: Option<String>,
+    /// Path to the manifest of the crate to build
+    #[arg(long)]
+    manifest_path: Option<PathBuf>,
+    /// Arguments passed through to `cargo build`
+    #[arg(last = true)]
+    cargo_args: Vec<String>,
+}
+
+pub fn run(args: BuildArgs) -> Result<()> {
+    for module in build(&args)? {
+        println!("{}", module.display());
+    }
+
+    Ok(())
+}
+
+/// Build the crate, and return the paths of the wasm modules produced
+pub fn build(args: &BuildArgs) -> Result<Vec<PathBuf>> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut command = Command::new(cargo);
+    command.args([
+        "build",
+        "--target",
+        TARGET,
+        "--message-format=json-render-diagnostics",
+    ]);
+    match (&args.profile, args.debug) {
+        (Some(profile), _) => command.args(["--profile", profile]),
+        (None, true) => &mut command,
+        (None, false) => command.arg("--release"),
+    };
+    if let Some(package) = &args.package {
+        command.args(["--package", package]);
+    }
+    if let Some(manifest_path) = &args.manifest_path {
+        command.arg("--manifest-path").arg(manifest_path);
+    }
+    command.args(&args.cargo_args).stdout(Stdio::piped());
+
+    let output = command
+        .spawn()
+        .and_then(|child| child.wait_with_output())
+        .context("failed to run cargo")?;
+    if !output.status.success() {
+        bail!("cargo build failed");
+    }
+
+    let modules = wasm_artifacts(&output.stdout)?;
+    if modules.is_empty() {
+        bail!("no wasm module was built, make sure the crate type includes `cdylib`");
+    }
+
+    Ok(modules)
+}
+
+/// Wasm modules listed in the JSON messages emitted by cargo
+fn wasm_artifacts(messages: &[u8]) -> Result<Vec<PathBuf>> {
+    let mut modules = Vec::new();
+    for line in messages.split(|&b| b == b'\n').filter(|l| !l.is_empty()) {
+        let message: serde_json::Value =
+            serde_json::from_slice(line).context("failed to read cargo output")?;
+        if message["reason"] != "compiler-artifact" {
+            continue;
+        }
+        if let Some(filenames) = message["filenames"].as_array() {
+            modules.extend(
+                filenames
+                    .iter()
+                    .filter_map(|filename| filename.as_str())
+                    .filter(|filename| filename.ends_with(".wasm"))
+                    .map(PathBuf::from),
+            );
+        }
+    }
+    Ok(modules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_list_wasm_artifacts() {
+        let messages = br#"{"reason":"compiler-artifact","filenames":["/t/libserde.rlib"]}
+{"reason":"compiler-artifact","filenames":["/t/transformations.wasm","/t/libtransformations.rlib"]}
+{"reason":"build-finished","success":true}
+"#;
+
+        assert_eq!(
+            wasm_artifacts(messages).unwrap(),
+            vec![PathBuf::from("/t/transformations.wasm")]
+        );
+    }
+}
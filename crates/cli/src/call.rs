@@ -0,0 +1,155 @@
+use crate::ModuleArgs;
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use holium_host::{Config, Host};
+use holium_rs_sdk::internal::data_tree;
+use holium_rs_sdk::internal::key_tree;
+use holium_rs_sdk::internal::manifest::{KeyShape, KeyTree};
+use std::io::Read;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct CallArgs {
+    #[command(flatten)]
+    module: ModuleArgs,
+    /// Name the transformation is exported under
+    export: String,
+    /// JSON file holding the inputs, `-` to read them from the standard input. Inputs are either
+    /// an object keyed by parameter names or an array holding one value per parameter.
+    #[arg(short, long)]
+    input: PathBuf,
+    /// Fuel given to the call, unlimited if 0
+    #[arg(long, default_value_t = Config::default().fuel.unwrap_or(0))]
+    fuel: u64,
+    /// Maximum size of the memory of the module, in bytes
+    #[arg(long, default_value_t = Config::default().max_memory)]
+    max_memory: usize,
+}
+
+pub fn run(args: CallArgs) -> Result<()> {
+    let wasm = args.module.read()?;
+    let host = Host::new(Config {
+        max_memory: args.max_memory,
+        fuel: Some(args.fuel).filter(|&fuel| fuel > 0),
+    });
+    let module = host.load(&wasm)?;
+
+    let inputs = if args.input.as_os_str() == "-" {
+        let mut json = String::new();
+        std::io::stdin().read_to_string(&mut json)?;
+        json
+    } else {
+        std::fs::read_to_string(&args.input)
+            .with_context(|| format!("failed to read {}", args.input.display()))?
+    };
+
+    let (input_keys, output_keys) = module.key_trees(&args.export)?;
+    let payload = data_tree::Node::from_json(&inputs, &key_node(input_keys)?)
+        .context("inputs do not match the parameters of the transformation")?;
+
+    let output = module
+        .call(&args.export, &payload)?
+        .to_json_pretty_with_keys(&key_node(output_keys)?)
+        .context("output cannot be converted to JSON")?;
+    println!("{}", output);
+
+    Ok(())
+}
+
+/// Key tree node equivalent to a key tree read from a module. The key tree is leaked, as nodes
+/// only hold static keys, which is fine for a single call.
+///
+/// Recursive nodes are resolved against the closest node above them labeled with the name of the
+/// type they reference. Key trees holding a recursive node without such label are rejected.
+fn key_node(tree: KeyTree) -> Result<key_tree::Node> {
+    key_node_in(Box::leak(Box::new(tree)), &[])
+}
+
+/// Key tree node equivalent to a node of a leaked key tree, given the nodes above it
+fn key_node_in(tree: &'static KeyTree, ancestors: &[&'static KeyTree]) -> Result<key_tree::Node> {
+    let shape = match &tree.shape {
+        KeyShape::Plain => key_tree::Shape::Plain,
+        KeyShape::Sequence => key_tree::Shape::Sequence,
+        KeyShape::Array(len) => key_tree::Shape::Array(*len),
+        KeyShape::Enum => key_tree::Shape::Enum,
+        KeyShape::Map => key_tree::Shape::Map,
+        KeyShape::Flattened => key_tree::Shape::Flattened,
+        KeyShape::Tuple => key_tree::Shape::Tuple,
+        KeyShape::Recursive(type_name) => {
+            let position = ancestors
+                .iter()
+                .rposition(|ancestor| ancestor.type_name.as_ref() == Some(type_name))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "key tree references the recursive type `{}` outside of its definition",
+                        type_name
+                    )
+                })?;
+            let (definition, definition_ancestors) =
+                (ancestors[position], ancestors[..position].to_vec());
+            key_tree::Shape::Recursive(key_tree::Reference {
+                type_name,
+                // Definition was already converted once with the same ancestors, so it
+                // converts again
+                generate: Box::leak(Box::new(move || {
+                    key_node_in(definition, &definition_ancestors)
+                        .expect("recursive definition was already converted")
+                })),
+            })
+        }
+    };
+
+    let ancestors = [ancestors, &[tree]].concat();
+    Ok(key_tree::Node {
+        value: tree.value.as_deref(),
+        children: tree
+            .children
+            .iter()
+            .map(|child| key_node_in(child, &ancestors))
+            .collect::<Result<_>>()?,
+        shape,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holium_rs_sdk::internal::key_tree::{GenerateNode, Recursion, StaticNode};
+    use holium_rs_sdk::testing::fixtures::Chain;
+
+    #[test]
+    fn can_convert_key_trees_from_modules() {
+        const NODE: StaticNode = StaticNode {
+            children: &[StaticNode {
+                value: Some("values"),
+                ..<Vec<(u32, [u8; 2])>>::KEY_NODE
+            }],
+            ..StaticNode::DEFAULT
+        };
+
+        assert_eq!(
+            key_node(KeyTree::from(&NODE)).unwrap(),
+            key_tree::Node::from(&NODE)
+        );
+    }
+
+    #[test]
+    fn can_resolve_recursive_key_trees() {
+        let node = key_node(KeyTree::from(&<Vec<Chain>>::KEY_NODE)).unwrap();
+
+        assert_eq!(node, <Vec<Chain>>::generate_node());
+        match node.children[0].children[1].shape {
+            key_tree::Shape::Recursive(reference) => {
+                assert_eq!((reference.generate)(), Chain::generate_node())
+            }
+            shape => panic!("unexpected shape: {:?}", shape),
+        }
+    }
+
+    #[test]
+    fn cannot_resolve_dangling_recursive_key_trees() {
+        let tree = KeyTree::from(&<Vec<Recursion<Chain>>>::KEY_NODE);
+
+        assert!(key_node(tree).is_err());
+    }
+}
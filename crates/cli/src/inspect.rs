@@ -0,0 +1,35 @@
+use crate::ModuleArgs;
+use anyhow::Result;
+use holium_host::Host;
+use holium_rs_sdk::internal::manifest::TypedName;
+
+pub fn run(args: ModuleArgs) -> Result<()> {
+    let module = Host::default().load(&args.read()?)?;
+
+    println!("SDK version: {}", module.sdk_version().unwrap_or("unknown"));
+    if module.transformations().is_empty() {
+        println!("No transformation found");
+    }
+    for transformation in module.transformations() {
+        println!();
+        println!(
+            "{}({}) -> ({})",
+            transformation.name,
+            signature(&transformation.parameters),
+            signature(&transformation.outputs)
+        );
+        for line in transformation.docs.lines() {
+            println!("    {}", line.trim());
+        }
+    }
+
+    Ok(())
+}
+
+fn signature(names: &[TypedName]) -> String {
+    names
+        .iter()
+        .map(|name| format!("{}: {}", name.name, name.ty))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,68 @@
+//! `cargo holium`, a cargo subcommand building, inspecting and calling Holium transformations.
+//!
+//! - `cargo holium build` compiles the current crate to a wasm module, for the
+//!   `wasm32-unknown-unknown` target and with the release profile unless told otherwise ;
+//! - `cargo holium inspect` prints the version of the SDK a module was compiled with and the
+//!   transformations it exports ;
+//! - `cargo holium call <export> --input <file>` runs a transformation locally on JSON inputs and
+//!   prints its JSON output.
+//!
+//! `inspect` and `call` work on the module built from the current crate as `build` does, unless
+//! another one is given with `--module <path>`.
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser};
+use std::path::PathBuf;
+
+mod build;
+mod call;
+mod inspect;
+
+/// Cargo invokes subcommands with their name as a first argument
+#[derive(Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum Cargo {
+    #[command(subcommand, version, about)]
+    Holium(Command),
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Compile the crate to a wasm module holding its transformations
+    Build(build::BuildArgs),
+    /// Print the SDK version and the transformations of a wasm module
+    Inspect(ModuleArgs),
+    /// Run a transformation of a wasm module on JSON inputs
+    Call(call::CallArgs),
+}
+
+#[derive(Args)]
+struct ModuleArgs {
+    /// Path to the wasm module, built from the current crate in release mode if omitted
+    #[arg(short, long)]
+    module: Option<PathBuf>,
+}
+
+impl ModuleArgs {
+    /// Bytes of the wasm module, built first if no path was given
+    fn read(&self) -> Result<Vec<u8>> {
+        let path = match &self.module {
+            Some(path) => path.clone(),
+            None => match build::build(&build::BuildArgs::default())?.as_slice() {
+                [module] => module.clone(),
+                _ => bail!("several wasm modules were built, pick one with --module"),
+            },
+        };
+        std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))
+    }
+}
+
+fn main() -> Result<()> {
+    let Cargo::Holium(command) = Cargo::parse();
+
+    match command {
+        Command::Build(args) => build::run(args),
+        Command::Inspect(args) => inspect::run(args),
+        Command::Call(args) => call::run(args),
+    }
+}
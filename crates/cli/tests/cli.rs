@@ -0,0 +1,124 @@
+use holium_rs_sdk::testing::fixtures;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const INPUT: &str = r#"{"chain": {"value": 1, "next": {"value": 2, "next": null}}}"#;
+
+/// Directory private to a test, emptied first
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cargo-holium-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write the shared module following the ABI of the SDK, whose `echo` transformation returns its
+/// input
+fn write_module(dir: &Path) -> PathBuf {
+    let path = dir.join("transformations.wasm");
+    std::fs::write(&path, wat::parse_str(fixtures::module_wat()).unwrap()).unwrap();
+    path
+}
+
+/// Run `cargo holium` with the given arguments and standard input
+fn cargo_holium(args: &[&str], stdin: &str, configure: impl FnOnce(&mut Command)) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_cargo-holium"));
+    command
+        .arg("holium")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    configure(&mut command);
+
+    let mut child = command.spawn().unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn json(output: &Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn can_inspect_module() {
+    let module = write_module(&test_dir("inspect"));
+
+    let output = cargo_holium(
+        &["inspect", "--module", module.to_str().unwrap()],
+        "",
+        |_| {},
+    );
+
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "SDK version: 1.0.0\n\necho(chain: Chain) -> (0: Chain)\n    Return its input\n"
+    );
+}
+
+#[test]
+fn can_call_transformation() {
+    let dir = test_dir("call");
+    let module = write_module(&dir);
+    let input = dir.join("input.json");
+    std::fs::write(&input, INPUT).unwrap();
+
+    let output = cargo_holium(
+        &[
+            "call",
+            "echo",
+            "--module",
+            module.to_str().unwrap(),
+            "--input",
+            input.to_str().unwrap(),
+        ],
+        "",
+        |_| {},
+    );
+
+    assert_eq!(
+        json(&output),
+        serde_json::json!([{"value": 1, "next": {"value": 2, "next": null}}])
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn can_call_transformation_of_built_module() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = test_dir("build");
+    let module = write_module(&dir);
+    // Stand-in for cargo, reporting the module as the artifact of the build
+    let cargo = dir.join("cargo");
+    std::fs::write(
+        &cargo,
+        format!(
+            "#!/bin/sh\necho '{}'\n",
+            serde_json::json!({"reason": "compiler-artifact", "filenames": [module]})
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&cargo, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = cargo_holium(&["call", "echo", "--input", "-"], INPUT, |command| {
+        command.env("CARGO", &cargo);
+    });
+
+    assert_eq!(
+        json(&output),
+        serde_json::json!([{"value": 1, "next": {"value": 2, "next": null}}])
+    );
+}
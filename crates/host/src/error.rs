@@ -0,0 +1,51 @@
+//! Errors that can arise while a host loads a module or calls one of its transformations
+
+use holium_rs_sdk::internal::error::{Error, Failure};
+use wasmi::core::TrapCode;
+use wasmi::errors::ErrorKind;
+
+#[derive(thiserror::Error, Debug)]
+pub enum HostError {
+    /// Module could not be parsed, validated or instantiated
+    #[error("failed to load module: {0}")]
+    Load(String),
+    /// Module does not export an item the host relies on
+    #[error("module does not export `{0}`")]
+    MissingExport(String),
+    /// Transformation is not listed in the manifest of the module
+    #[error("transformation `{0}` is not listed in the manifest of the module")]
+    NotInManifest(String),
+    /// Transformation consumed all the fuel it was given
+    #[error("transformation ran out of fuel")]
+    OutOfFuel,
+    /// Transformation tried to grow its memory beyond the configured limit
+    #[error("transformation exceeded its memory limit")]
+    MemoryLimit,
+    /// Transformation trapped while running
+    #[error("transformation trapped: {0}")]
+    Trap(String),
+    /// Transformation panicked, as recorded by the SDK before the instance trapped
+    #[error("transformation panicked at {location}: {message}")]
+    Panic { message: String, location: String },
+    /// Transformation could not be run, as reported by its shim
+    #[error(transparent)]
+    Guest(#[from] Error),
+    /// Transformation returned a `Result::Err`
+    #[error("transformation failed: {}", .0.message)]
+    Failure(Failure),
+    /// Data exchanged with the module could not be encoded or decoded
+    #[error("failed to exchange data with the module: {0}")]
+    Data(String),
+}
+
+impl From<wasmi::Error> for HostError {
+    fn from(error: wasmi::Error) -> Self {
+        match (error.as_trap_code(), error.kind()) {
+            (Some(TrapCode::OutOfFuel), _) => HostError::OutOfFuel,
+            (Some(TrapCode::GrowthOperationLimited), _) | (_, ErrorKind::Memory(_)) => {
+                HostError::MemoryLimit
+            }
+            _ => HostError::Trap(error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,429 @@
+//! Holium host.
+//!
+//! Reference runtime loading wasm modules compiled with the Holium Rust SDK and calling their
+//! transformations locally, as the Holium platform would. Guest buffers are handled as described
+//! in [`memory`](holium_rs_sdk::internal::memory).
+//!
+//! A transformation panicking traps the instance it runs in, as wasm targets abort on panic. The
+//! host then queries the panic recorded by the SDK on that same instance, following the protocol
+//! described in [`panic`](holium_rs_sdk::internal::panic), and reports it as a
+//! [`HostError::Panic`].
+//!
+//! Every call runs in a fresh instance of the module, whose memory is limited to
+//! [`Config::max_memory`] bytes and which is interrupted once it consumed [`Config::fuel`] units of
+//! fuel, roughly one per executed instruction.
+//!
+//! Input and output data trees can be content-addressed with
+//! [`Node::merkle_tree`](holium_rs_sdk::internal::data_tree::Node::merkle_tree), for instance to
+//! cache the output of a transformation by the CID of its input.
+
+pub use error::HostError;
+
+use holium_rs_sdk::internal::data_tree;
+use holium_rs_sdk::internal::error::Error;
+use holium_rs_sdk::internal::manifest::{self, KeyTree, Transformation};
+use holium_rs_sdk::internal::memory::{Status, WASM32_OUTPUT_SIZE};
+use std::convert::TryFrom;
+use wasmi::{Engine, Instance, Linker, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+mod error;
+
+/// Name of the custom section holding the version of the SDK a module was compiled with
+pub const SDK_VERSION_SECTION_NAME: &str = "__holium_sdk_version";
+
+/// Limits applied to every call of a transformation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Maximum size of the linear memory of a module, in bytes
+    pub max_memory: usize,
+    /// Fuel given to a call, unlimited if `None`
+    pub fuel: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_memory: 256 * 1024 * 1024,
+            fuel: Some(10_000_000_000),
+        }
+    }
+}
+
+/// Runtime loading modules with a given configuration
+pub struct Host {
+    engine: Engine,
+    config: Config,
+}
+
+impl Host {
+    pub fn new(config: Config) -> Self {
+        let mut engine_config = wasmi::Config::default();
+        engine_config.consume_fuel(config.fuel.is_some());
+
+        Host {
+            engine: Engine::new(&engine_config),
+            config,
+        }
+    }
+
+    /// Load a module from its wasm bytes
+    pub fn load(&self, wasm: &[u8]) -> Result<Module, HostError> {
+        let module =
+            wasmi::Module::new(&self.engine, wasm).map_err(|e| HostError::Load(e.to_string()))?;
+
+        let mut sdk_version = None;
+        let mut manifest_section = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+            if let wasmparser::Payload::CustomSection(section) =
+                payload.map_err(|e| HostError::Load(e.to_string()))?
+            {
+                match section.name() {
+                    SDK_VERSION_SECTION_NAME => {
+                        sdk_version = Some(
+                            String::from_utf8(section.data().to_vec())
+                                .map_err(|e| HostError::Load(e.to_string()))?,
+                        )
+                    }
+                    manifest::SECTION_NAME => manifest_section.extend_from_slice(section.data()),
+                    _ => {}
+                }
+            }
+        }
+        let transformations = manifest::parse_section(&manifest_section)
+            .map_err(|e| HostError::Load(e.to_string()))?;
+
+        Ok(Module {
+            engine: self.engine.clone(),
+            config: self.config,
+            module,
+            sdk_version,
+            transformations,
+        })
+    }
+}
+
+impl Default for Host {
+    fn default() -> Self {
+        Host::new(Config::default())
+    }
+}
+
+/// Data held by the store of an instance
+struct State {
+    limits: StoreLimits,
+}
+
+/// Module loaded by a host
+pub struct Module {
+    engine: Engine,
+    config: Config,
+    module: wasmi::Module,
+    sdk_version: Option<String>,
+    transformations: Vec<Transformation>,
+}
+
+impl Module {
+    /// Version of the SDK the module was compiled with, if embedded in the module
+    pub fn sdk_version(&self) -> Option<&str> {
+        self.sdk_version.as_deref()
+    }
+
+    /// Transformations listed in the manifest of the module
+    pub fn transformations(&self) -> &[Transformation] {
+        &self.transformations
+    }
+
+    /// Call the transformation exported under the given name with an input data tree, and return
+    /// its output data tree
+    pub fn call(
+        &self,
+        export: &str,
+        payload: &data_tree::Node,
+    ) -> Result<data_tree::Node, HostError> {
+        let payload_u8 = payload
+            .encode()
+            .map_err(|e| HostError::Data(e.to_string()))?;
+        let bytes = self.call_bytes(export, &payload_u8)?;
+        serde_cbor::from_slice(&bytes).map_err(|e| HostError::Data(e.to_string()))
+    }
+
+    /// Call the transformation exported under the given name with raw payload bytes, and return
+    /// the bytes of its CBOR-encoded output data tree
+    pub fn call_bytes(&self, export: &str, payload: &[u8]) -> Result<Vec<u8>, HostError> {
+        let mut instance = self.instantiate()?;
+        let shim: TypedFunc<(i32, i32, i32), ()> = instance.func(export)?;
+
+        let input_ptr = instance.write(payload)?;
+        let output_ptr = instance.alloc_output()?;
+        if let Err(e) = shim.call(
+            &mut instance.store,
+            (output_ptr, input_ptr, wasm_len(payload.len())?),
+        ) {
+            return Err(match HostError::from(e) {
+                HostError::Trap(message) => {
+                    instance.last_panic()?.unwrap_or(HostError::Trap(message))
+                }
+                error => error,
+            });
+        }
+        let (status, bytes) = instance.read_output(output_ptr)?;
+        instance.free(input_ptr, payload.len())?;
+
+        decode_output(status, bytes)
+    }
+
+    /// Input and output key trees of the transformation exported under the given name, as listed
+    /// in the manifest of the module
+    pub fn key_trees(&self, export: &str) -> Result<(KeyTree, KeyTree), HostError> {
+        self.transformations
+            .iter()
+            .find(|transformation| transformation.name == export)
+            .map(|transformation| transformation.key_trees.clone())
+            .ok_or_else(|| HostError::NotInManifest(String::from(export)))
+    }
+
+    /// Fresh instance of the module, limited as configured
+    fn instantiate(&self) -> Result<GuestInstance, HostError> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.config.max_memory)
+            .trap_on_grow_failure(true)
+            .build();
+        let mut store = Store::new(&self.engine, State { limits });
+        store.limiter(|state| &mut state.limits);
+        if let Some(fuel) = self.config.fuel {
+            store
+                .set_fuel(fuel)
+                .map_err(|e| HostError::Load(e.to_string()))?;
+        }
+
+        let instance = Linker::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| match HostError::from(e) {
+                HostError::Trap(message) => HostError::Load(message),
+                error => error,
+            })?;
+
+        Ok(GuestInstance { store, instance })
+    }
+}
+
+/// Instance of a module, with helpers to exchange buffers with it
+struct GuestInstance {
+    store: Store<State>,
+    instance: Instance,
+}
+
+impl GuestInstance {
+    fn func<Params, Results>(&self, name: &str) -> Result<TypedFunc<Params, Results>, HostError>
+    where
+        Params: wasmi::WasmParams,
+        Results: wasmi::WasmResults,
+    {
+        self.instance
+            .get_typed_func(&self.store, name)
+            .map_err(|_| HostError::MissingExport(String::from(name)))
+    }
+
+    fn memory(&self) -> Result<wasmi::Memory, HostError> {
+        self.instance
+            .get_memory(&self.store, "memory")
+            .ok_or_else(|| HostError::MissingExport(String::from("memory")))
+    }
+
+    fn alloc(&mut self, len: usize) -> Result<i32, HostError> {
+        let alloc: TypedFunc<i32, i32> = self.func("__hbindgen_mem_alloc")?;
+        Ok(alloc.call(&mut self.store, wasm_len(len)?)?)
+    }
+
+    fn free(&mut self, ptr: i32, len: usize) -> Result<(), HostError> {
+        let free: TypedFunc<(i32, i32), ()> = self.func("__hbindgen_mem_free")?;
+        Ok(free.call(&mut self.store, (ptr, wasm_len(len)?))?)
+    }
+
+    /// Allocate the area a shim writes its output to, aligned as the guest expects it
+    fn alloc_output(&mut self) -> Result<i32, HostError> {
+        let alloc: TypedFunc<(), i32> = self.func("__hbindgen_output_alloc")?;
+        Ok(alloc.call(&mut self.store, ())?)
+    }
+
+    fn free_output(&mut self, ptr: i32) -> Result<(), HostError> {
+        let free: TypedFunc<i32, ()> = self.func("__hbindgen_output_free")?;
+        Ok(free.call(&mut self.store, ptr)?)
+    }
+
+    /// Copy bytes into a new buffer of the guest memory, and return its pointer
+    fn write(&mut self, bytes: &[u8]) -> Result<i32, HostError> {
+        let ptr = self.alloc(bytes.len())?;
+        self.memory()?
+            .write(&mut self.store, ptr as u32 as usize, bytes)
+            .map_err(|e| HostError::Data(e.to_string()))?;
+        Ok(ptr)
+    }
+
+    fn read(&self, ptr: usize, len: usize) -> Result<Vec<u8>, HostError> {
+        let mut bytes = vec![0; len];
+        self.memory()?
+            .read(&self.store, ptr, &mut bytes)
+            .map_err(|e| HostError::Data(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Read the output written by a shim at the given pointer, then release both the output and
+    /// the buffer it points to
+    fn read_output(&mut self, output_ptr: i32) -> Result<(u32, Vec<u8>), HostError> {
+        let output = self.read(output_ptr as u32 as usize, WASM32_OUTPUT_SIZE)?;
+        let word =
+            |i: usize| u32::from_le_bytes([output[i], output[i + 1], output[i + 2], output[i + 3]]);
+        let (status, ptr, len) = (word(0), word(4) as usize, word(8) as usize);
+
+        let bytes = self.read(ptr, len)?;
+        self.free(ptr as u32 as i32, len)?;
+        self.free_output(output_ptr)?;
+
+        Ok((status, bytes))
+    }
+
+    /// Panic recorded by the SDK before the instance trapped, if any. Modules that do not export
+    /// `__hbindgen_last_panic` have no record.
+    fn last_panic(&mut self) -> Result<Option<HostError>, HostError> {
+        let last_panic: TypedFunc<i32, ()> = match self.func("__hbindgen_last_panic") {
+            Ok(last_panic) => last_panic,
+            Err(_) => return Ok(None),
+        };
+        let output_ptr = self.alloc_output()?;
+        last_panic.call(&mut self.store, output_ptr)?;
+        let (status, bytes) = self.read_output(output_ptr)?;
+
+        match decode_output(status, bytes) {
+            Ok(_) => Ok(None),
+            Err(error @ HostError::Panic { .. }) => Ok(Some(error)),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Length of a buffer as a wasm32 value
+fn wasm_len(len: usize) -> Result<i32, HostError> {
+    u32::try_from(len)
+        .map(|len| len as i32)
+        .map_err(|_| HostError::MemoryLimit)
+}
+
+/// Bytes of a successful output, or the error it holds
+fn decode_output(status: u32, bytes: Vec<u8>) -> Result<Vec<u8>, HostError> {
+    match status {
+        s if s == Status::Success as u32 => Ok(bytes),
+        s if s == Status::Error as u32 => {
+            match serde_cbor::from_slice(&bytes).map_err(|e| HostError::Data(e.to_string()))? {
+                Error::Panic {
+                    message, location, ..
+                } => Err(HostError::Panic { message, location }),
+                error => Err(HostError::Guest(error)),
+            }
+        }
+        s if s == Status::Failure as u32 => Err(HostError::Failure(
+            serde_cbor::from_slice(&bytes).map_err(|e| HostError::Data(e.to_string()))?,
+        )),
+        s => Err(HostError::Data(format!("unknown output status {}", s))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holium_rs_sdk::testing::fixtures;
+
+    fn module(host: &Host) -> Module {
+        host.load(&wat::parse_str(fixtures::module_wat()).unwrap())
+            .unwrap()
+    }
+
+    fn limited_host() -> Host {
+        Host::new(Config {
+            max_memory: 2 * 64 * 1024,
+            fuel: Some(1_000_000),
+        })
+    }
+
+    #[test]
+    fn can_read_sections() {
+        let module = module(&Host::default());
+
+        assert_eq!(module.sdk_version(), Some("1.0.0"));
+        assert_eq!(module.transformations().len(), 1);
+        assert_eq!(module.transformations()[0].name, "echo");
+        assert_eq!(module.transformations()[0].parameters[0].name, "chain");
+    }
+
+    #[test]
+    fn can_call_transformation() {
+        let module = module(&Host::default());
+        let payload = data_tree::Node::from_serializable(&(42u32, "holium")).unwrap();
+
+        assert_eq!(module.call("echo", &payload).unwrap(), payload);
+    }
+
+    #[test]
+    fn can_read_key_trees() {
+        let module = module(&Host::default());
+
+        let (input, output) = module.key_trees("echo").unwrap();
+
+        assert_eq!(input.children[0].value.as_deref(), Some("chain"));
+        assert_eq!(output.children[0].value.as_deref(), Some("0"));
+        assert!(matches!(
+            module.key_trees("spin"),
+            Err(HostError::NotInManifest(_))
+        ));
+    }
+
+    #[test]
+    fn can_report_guest_errors() {
+        let module = module(&Host::default());
+        let payload = data_tree::Node::from_serializable(&(42u32,)).unwrap();
+
+        assert!(matches!(
+            module.call("error", &payload),
+            Err(HostError::Guest(Error::PayloadDecode(_)))
+        ));
+        assert!(matches!(
+            module.call("missing", &payload),
+            Err(HostError::MissingExport(_))
+        ));
+    }
+
+    #[test]
+    fn can_report_panics_after_trap() {
+        let module = module(&Host::default());
+        let payload = data_tree::Node::from_serializable(&(42u32,)).unwrap();
+
+        match module.call("panic", &payload) {
+            Err(HostError::Panic { message, location }) => {
+                assert_eq!(message, "index out of bounds");
+                assert_eq!(location, "src/lib.rs:3");
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert!(matches!(
+            module.call("trap", &payload),
+            Err(HostError::Trap(_))
+        ));
+    }
+
+    #[test]
+    fn cannot_exceed_limits() {
+        let module = module(&limited_host());
+        let payload = data_tree::Node::from_serializable(&(42u32,)).unwrap();
+
+        assert!(matches!(
+            module.call("spin", &payload),
+            Err(HostError::OutOfFuel)
+        ));
+        assert!(matches!(
+            module.call("grow", &payload),
+            Err(HostError::MemoryLimit)
+        ));
+        assert_eq!(module.call("echo", &payload).unwrap(), payload);
+    }
+}
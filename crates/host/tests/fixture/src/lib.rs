@@ -0,0 +1,40 @@
+//! Transformations compiled with the Holium Rust SDK to `wasm32-unknown-unknown`, checking that
+//! the host follows the ABI of the shims generated by the SDK.
+
+use holium_rs_sdk::holium_bindgen;
+use serde::Serialize;
+
+#[holium_bindgen]
+pub struct Values {
+    pub a: u32,
+    pub b: u32,
+}
+
+#[holium_bindgen]
+pub fn add(values: Values) -> u32 {
+    values.a + values.b
+}
+
+#[derive(Serialize)]
+pub struct OddNumber {
+    pub value: u32,
+}
+
+impl std::fmt::Display for OddNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} is odd", self.value)
+    }
+}
+
+#[holium_bindgen]
+pub fn halve(value: u32) -> Result<u32, OddNumber> {
+    match value % 2 {
+        0 => Ok(value / 2),
+        _ => Err(OddNumber { value }),
+    }
+}
+
+#[holium_bindgen]
+pub fn divide(numerator: u32, denominator: u32) -> u32 {
+    numerator / denominator
+}
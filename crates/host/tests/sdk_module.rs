@@ -0,0 +1,99 @@
+//! Calls of a module compiled with the Holium Rust SDK, checking that the host follows the ABI of
+//! the generated shims and the panic protocol of the SDK on a target aborting on panic.
+//!
+//! The module is built from the `fixture` crate, which requires the `wasm32-unknown-unknown`
+//! target. Tests are thus ignored by default and run once the module is built:
+//!
+//! ```sh
+//! cargo build --manifest-path crates/host/tests/fixture/Cargo.toml \
+//!     --target wasm32-unknown-unknown --release --target-dir target/fixture
+//! cargo test -p holium-host --test sdk_module -- --ignored
+//! ```
+
+use holium_host::{Host, HostError};
+use holium_rs_sdk::internal::data_tree;
+use holium_rs_sdk::internal::manifest::KeyShape;
+
+const FIXTURE_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../target/fixture/wasm32-unknown-unknown/release/holium_host_fixture.wasm"
+);
+
+fn module() -> holium_host::Module {
+    let wasm = std::fs::read(FIXTURE_PATH)
+        .unwrap_or_else(|e| panic!("fixture module not built at {}: {}", FIXTURE_PATH, e));
+    Host::default().load(&wasm).unwrap()
+}
+
+#[test]
+#[ignore = "requires the fixture module built for wasm32-unknown-unknown"]
+fn can_read_manifest() {
+    let module = module();
+
+    assert_eq!(module.sdk_version(), Some("1.0.0"));
+    let mut names: Vec<_> = module
+        .transformations()
+        .iter()
+        .map(|transformation| transformation.name.as_str())
+        .collect();
+    names.sort_unstable();
+    assert_eq!(names, ["add", "divide", "halve"]);
+
+    let (input, output) = module.key_trees("add").unwrap();
+    assert_eq!(input.children[0].value.as_deref(), Some("values"));
+    let fields: Vec<_> = input.children[0]
+        .children
+        .iter()
+        .map(|child| child.value.as_deref())
+        .collect();
+    assert_eq!(fields, [Some("a"), Some("b")]);
+    assert!(matches!(output.shape, KeyShape::Tuple));
+}
+
+#[test]
+#[ignore = "requires the fixture module built for wasm32-unknown-unknown"]
+fn can_call_transformation() {
+    let module = module();
+    let payload = data_tree::Node::from_serializable(&((1u32, 2u32),)).unwrap();
+
+    let output = module.call("add", &payload).unwrap();
+
+    assert_eq!(output.deserialize::<(u32,)>().unwrap(), (3,));
+}
+
+#[test]
+#[ignore = "requires the fixture module built for wasm32-unknown-unknown"]
+fn can_report_failures() {
+    let module = module();
+    let payload = data_tree::Node::from_serializable(&(3u32,)).unwrap();
+
+    match module.call("halve", &payload) {
+        Err(HostError::Failure(failure)) => assert_eq!(failure.message, "3 is odd"),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+#[ignore = "requires the fixture module built for wasm32-unknown-unknown"]
+fn can_report_panics() {
+    let module = module();
+    let payload = data_tree::Node::from_serializable(&(1u32, 0u32)).unwrap();
+
+    match module.call("divide", &payload) {
+        Err(HostError::Panic { message, location }) => {
+            assert_eq!(message, "attempt to divide by zero");
+            assert!(location.starts_with("src/lib.rs:"), "{}", location);
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+    // A fresh instance is used for every call, so the module can still be called
+    let payload = data_tree::Node::from_serializable(&(4u32, 2u32)).unwrap();
+    assert_eq!(
+        module
+            .call("divide", &payload)
+            .unwrap()
+            .deserialize::<(u32,)>()
+            .unwrap(),
+        (2,)
+    );
+}
@@ -6,22 +6,24 @@ extern crate syn;
 #[macro_use]
 extern crate holium_backend as backend;
 
-use crate::parser::MacroParse;
+use crate::parser::{BindgenAttrs, MacroParse};
 use backend::{Diagnostic, TryToTokens};
 use proc_macro2::TokenStream;
 
 mod parser;
+mod serde_attrs;
 
 /// Takes the parsed input from a `#[holium_bindgen]` macro and returns the generated bindings
-pub fn expand(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+pub fn expand(attr: TokenStream, input: TokenStream) -> Result<TokenStream, Diagnostic> {
     let item = syn::parse2::<syn::Item>(input)?;
+    let opts = syn::parse2::<BindgenAttrs>(attr)?;
 
     let mut tokens = proc_macro2::TokenStream::new();
     let mut program = backend::ast::Program::default();
 
     // First step is to parse the `TokenStream` to copy source tokens & generate custom AST structures
     // for the codegen step
-    item.macro_parse(&mut program, &mut tokens)?;
+    item.macro_parse(&mut program, (opts, &mut tokens))?;
 
     // Second step is to generate code custom tokens based on custom AST structures & append it to
     // the `TokenStream`
@@ -1,10 +1,209 @@
 //! Parser reads a source `TokenStream` to prepare the backend to generate custom code
 
+use crate::serde_attrs::SerdeAttrs;
 use backend::ast;
 use backend::Diagnostic;
 use proc_macro2::TokenStream;
 use quote::ToTokens;
-use syn;
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::visit_mut::VisitMut;
+
+/// Arguments given to the `#[holium_bindgen]` attribute
+#[derive(Default)]
+pub(crate) struct BindgenAttrs {
+    /// Name a function is exported under, given by `name = "..."` or its `js_name = "..."` alias
+    name: Option<syn::LitStr>,
+    /// `skip` flag, leaving the item as it is
+    skip: Option<syn::Ident>,
+    /// Concrete instantiations of a generic function to export
+    instantiations: Vec<Instantiation>,
+    /// `outputs` keyword and the names it gives to the positions of a tuple output
+    outputs: Option<(syn::Ident, Vec<syn::Ident>)>,
+}
+
+/// Argument `instantiate(T = u32, name = "sum_u32")`, exporting a generic function under the given
+/// name with its type parameters replaced by concrete types
+struct Instantiation {
+    /// The `instantiate` keyword, to point at the instantiation in errors
+    keyword: syn::Ident,
+    /// The name the instantiation is exported under
+    name: syn::LitStr,
+    /// Concrete types given to each type parameter
+    types: Vec<(syn::Ident, syn::Type)>,
+}
+
+impl Parse for BindgenAttrs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs = BindgenAttrs::default();
+
+        while !input.is_empty() {
+            let keyword: syn::Ident = input.parse()?;
+            match keyword.to_string().as_str() {
+                "name" | "js_name" => {
+                    if attrs.name.is_some() {
+                        return Err(syn::Error::new(keyword.span(), "duplicate export name"));
+                    }
+                    input.parse::<syn::Token![=]>()?;
+                    attrs.name = Some(parse_name(input)?);
+                }
+                "skip" => {
+                    if attrs.skip.is_some() {
+                        return Err(syn::Error::new(keyword.span(), "duplicate `skip` flag"));
+                    }
+                    attrs.skip = Some(keyword);
+                }
+                "instantiate" => {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    attrs
+                        .instantiations
+                        .push(Instantiation::parse(keyword, &content)?);
+                }
+                "outputs" => {
+                    if attrs.outputs.is_some() {
+                        return Err(syn::Error::new(keyword.span(), "duplicate `outputs` list"));
+                    }
+                    let content;
+                    syn::parenthesized!(content in input);
+                    let names = parse_output_names(&keyword, &content)?;
+                    attrs.outputs = Some((keyword, names));
+                }
+                _ => return Err(syn::Error::new(keyword.span(), "unknown attribute")),
+            }
+
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+
+        Ok(attrs)
+    }
+}
+
+/// Parse the name an item is exported under
+fn parse_name(input: ParseStream) -> syn::Result<syn::LitStr> {
+    let name: syn::LitStr = input.parse()?;
+    if name.value().is_empty() {
+        return Err(syn::Error::new(name.span(), "export names cannot be empty"));
+    }
+    Ok(name)
+}
+
+/// Parse the names given to the positions of a tuple output
+fn parse_output_names(keyword: &syn::Ident, content: ParseStream) -> syn::Result<Vec<syn::Ident>> {
+    let names = content.parse_terminated::<_, syn::Token![,]>(syn::Ident::parse_any)?;
+    if names.is_empty() {
+        return Err(syn::Error::new(
+            keyword.span(),
+            "expected at least one output name",
+        ));
+    }
+
+    let mut outputs: Vec<syn::Ident> = Vec::new();
+    for name in names {
+        if outputs.iter().any(|output| output.unraw() == name.unraw()) {
+            return Err(syn::Error::new(name.span(), "duplicate output name"));
+        }
+        outputs.push(name);
+    }
+    Ok(outputs)
+}
+
+impl Instantiation {
+    fn parse(keyword: syn::Ident, content: ParseStream) -> syn::Result<Self> {
+        let mut name = None;
+        let mut types = Vec::new();
+
+        while !content.is_empty() {
+            let param: syn::Ident = content.parse()?;
+            content.parse::<syn::Token![=]>()?;
+            if param == "name" {
+                name = Some(parse_name(content)?);
+            } else {
+                types.push((param, content.parse()?));
+            }
+
+            if !content.is_empty() {
+                content.parse::<syn::Token![,]>()?;
+            }
+        }
+
+        let name = name.ok_or_else(|| {
+            syn::Error::new(
+                keyword.span(),
+                "instantiations need a `name` to be exported under",
+            )
+        })?;
+
+        Ok(Instantiation {
+            keyword,
+            name,
+            types,
+        })
+    }
+
+    /// Replace the type parameters of a generic function by the concrete types of this
+    /// instantiation. Also returns the concrete types, in the order of the parameters.
+    fn apply(&self, f: &syn::ItemFn) -> Result<(syn::ItemFn, Vec<syn::Type>), Diagnostic> {
+        let mut generics = Vec::new();
+        for param in f.sig.generics.params.iter() {
+            match param {
+                syn::GenericParam::Type(type_param) => {
+                    match self
+                        .types
+                        .iter()
+                        .find(|(ident, _)| *ident == type_param.ident)
+                    {
+                        Some((_, ty)) => generics.push(ty.clone()),
+                        None => bail_span!(
+                            self.keyword,
+                            "no type given to the type parameter `{}`",
+                            type_param.ident
+                        ),
+                    }
+                }
+                param => bail_span!(
+                    param,
+                    "only type parameters can be instantiated with #[holium_bindgen]"
+                ),
+            }
+        }
+        for (ident, _) in self.types.iter() {
+            let is_param = f.sig.generics.params.iter().any(
+                |param| matches!(param, syn::GenericParam::Type(type_param) if type_param.ident == *ident),
+            );
+            if !is_param {
+                bail_span!(ident, "no type parameter named `{}`", ident);
+            }
+        }
+
+        // The instantiated function is exported with concrete types
+        let mut instantiated = f.clone();
+        instantiated.sig.generics = syn::Generics::default();
+        InstantiateTypes(&self.types).visit_signature_mut(&mut instantiated.sig);
+
+        Ok((instantiated, generics))
+    }
+}
+
+/// Visitor replacing type parameters by concrete types
+struct InstantiateTypes<'a>(&'a [(syn::Ident, syn::Type)]);
+
+impl VisitMut for InstantiateTypes<'_> {
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        if let syn::Type::Path(syn::TypePath { qself: None, path }) = ty {
+            if let Some((_, concrete)) = path
+                .get_ident()
+                .and_then(|ident| self.0.iter().find(|(param, _)| param == ident))
+            {
+                *ty = concrete.clone();
+                return;
+            }
+        }
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+}
 
 /// Conversion trait with context.
 ///
@@ -18,31 +217,38 @@ trait ConvertToAst {
     fn convert(self) -> Result<Self::Target, Diagnostic>;
 }
 
-impl<'a> ConvertToAst for &'a mut syn::ItemStruct {
+impl ConvertToAst for &mut syn::ItemStruct {
     type Target = ast::Struct;
 
     fn convert(self) -> Result<Self::Target, Diagnostic> {
         // No lifetime to make sure that we can handle it correctly in a payload
-        if self.generics.params.len() > 0 {
+        if let Some(lifetime) = self.generics.lifetimes().next() {
             bail_span!(
-                self.generics,
-                "structs with #[holium_bindgen] cannot have lifetime or \
-                 type parameters currently"
+                lifetime,
+                "structs with #[holium_bindgen] cannot have lifetime parameters currently"
             );
         }
 
+        let serde_attrs = SerdeAttrs::parse(&self.attrs)?;
+
         // When handling struct, first create fields objects
         let mut fields = Vec::new();
         for (i, field) in self.fields.iter_mut().enumerate() {
-            // Fields visibility to be public to be taken into account
-            match field.vis {
-                syn::Visibility::Public(..) => {}
-                _ => continue,
+            serialize_arrays_with_helpers(field)?;
+
+            // Every field that is not skipped is serialized, whatever its visibility, and is thus
+            // part of the key tree
+            let field_attrs = SerdeAttrs::parse(&field.attrs)?;
+            if field_attrs.skip {
+                continue;
             }
 
-            // Derive field name from ident
+            // Derive field name from ident, the way serde does
             let (name, member) = match &field.ident {
-                Some(ident) => (ident.to_string(), syn::Member::Named(ident.clone())),
+                Some(ident) => (
+                    field_attrs.field_name(ident, serde_attrs.rename_all),
+                    syn::Member::Named(ident.clone()),
+                ),
                 None => (i.to_string(), syn::Member::Unnamed(i.into())),
             };
 
@@ -51,18 +257,123 @@ impl<'a> ConvertToAst for &'a mut syn::ItemStruct {
                 name,
                 struct_name: self.ident.clone(),
                 ty: field.ty.clone(),
+                flatten: field_attrs.flatten,
             });
         }
 
+        let style = match &self.fields {
+            syn::Fields::Named(_) => ast::StructStyle::Named,
+            syn::Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                ast::StructStyle::Newtype
+            }
+            syn::Fields::Unnamed(_) => ast::StructStyle::Tuple,
+            syn::Fields::Unit => ast::StructStyle::Unit,
+        };
+
         // Generate the AST object for the Struct
         Ok(ast::Struct {
             rust_name: self.ident.clone(),
             name: self.ident.to_string(),
             fields,
+            style,
+            generics: self.generics.clone(),
+        })
+    }
+}
+
+impl ConvertToAst for &mut syn::ItemEnum {
+    type Target = ast::Enum;
+
+    fn convert(self) -> Result<Self::Target, Diagnostic> {
+        // No lifetime to make sure that we can handle it correctly in a payload
+        if let Some(lifetime) = self.generics.lifetimes().next() {
+            bail_span!(
+                lifetime,
+                "enums with #[holium_bindgen] cannot have lifetime parameters currently"
+            );
+        }
+
+        // Serde requires the content of an adjacently tagged newtype variant to be deserializable
+        // on its own, arrays held by newtype variants are thus left to serde
+        for field in self
+            .variants
+            .iter_mut()
+            .filter(|variant| !matches!(&variant.fields, syn::Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1))
+            .flat_map(|variant| variant.fields.iter_mut())
+        {
+            serialize_arrays_with_helpers(field)?;
+        }
+
+        let serde_attrs = SerdeAttrs::parse(&self.attrs)?;
+
+        // For each variant, create its fields objects depending on its kind
+        let mut variants = Vec::new();
+        for variant in self.variants.iter() {
+            // Skipped variants are not part of the serialized form
+            let variant_attrs = SerdeAttrs::parse(&variant.attrs)?;
+            if variant_attrs.skip {
+                continue;
+            }
+
+            let fields = match &variant.fields {
+                syn::Fields::Named(named) => {
+                    let rename_rule = variant_attrs.rename_all.or(serde_attrs.rename_all_fields);
+
+                    let mut fields = Vec::new();
+                    for field in named.named.iter() {
+                        let field_attrs = SerdeAttrs::parse(&field.attrs)?;
+                        if let (false, Some(ident)) = (field_attrs.skip, &field.ident) {
+                            fields.push(ast::StructField {
+                                rust_name: syn::Member::Named(ident.clone()),
+                                name: field_attrs.field_name(ident, rename_rule),
+                                struct_name: self.ident.clone(),
+                                ty: field.ty.clone(),
+                                flatten: field_attrs.flatten,
+                            });
+                        }
+                    }
+                    ast::VariantFields::Named(fields)
+                }
+                syn::Fields::Unnamed(unnamed) => {
+                    let mut types = Vec::new();
+                    for field in unnamed.unnamed.iter() {
+                        if !SerdeAttrs::parse(&field.attrs)?.skip {
+                            types.push(field.ty.clone());
+                        }
+                    }
+                    ast::VariantFields::Unnamed(types)
+                }
+                syn::Fields::Unit => ast::VariantFields::Unit,
+            };
+
+            variants.push(ast::Variant {
+                rust_name: variant.ident.clone(),
+                name: variant_attrs.variant_name(&variant.ident, serde_attrs.rename_all),
+                fields,
+            });
+        }
+
+        // Generate the AST object for the Enum
+        Ok(ast::Enum {
+            rust_name: self.ident.clone(),
+            name: self.ident.to_string(),
+            variants,
+            generics: self.generics.clone(),
         })
     }
 }
 
+/// Serde cannot serialize arrays of more than 32 elements by itself. Have fields holding arrays
+/// serialized with our own helpers, unless they are already serialized in a custom way.
+fn serialize_arrays_with_helpers(field: &mut syn::Field) -> Result<(), Diagnostic> {
+    if let Some(attribute) = backend::array_serde_attribute(&field.ty) {
+        if !SerdeAttrs::parse(&field.attrs)?.custom {
+            field.attrs.push(attribute);
+        }
+    }
+    Ok(())
+}
+
 impl ConvertToAst for syn::ItemFn {
     type Target = ast::Function;
 
@@ -87,7 +398,8 @@ impl ConvertToAst for syn::ItemFn {
             );
         }
 
-        let f = function_from_decl(&self.sig.ident, self.sig.clone())?;
+        let mut f = function_from_decl(&self.sig.ident, self.sig.clone())?;
+        f.docs = docs(&self.attrs);
         Ok(f)
     }
 }
@@ -101,11 +413,13 @@ fn function_from_decl(
         bail_span!(sig.variadic, "can't #[holium_bindgen] variadic functions");
     }
 
-    // No lifetime or generics to make sure that we can handle it correctly in a payload
-    if sig.generics.params.len() > 0 {
+    // No lifetime or generics to make sure that we can handle it correctly in a payload. Generic
+    // functions have to be instantiated first.
+    if !sig.generics.params.is_empty() {
         bail_span!(
             sig.generics,
-            "can't #[holium_bindgen] functions with lifetime or type parameters",
+            "can't #[holium_bindgen] functions with lifetime or type parameters, type parameters \
+             can be given concrete types with `#[holium_bindgen(instantiate(T = u32, name = \"...\"))]`",
         );
     }
     // TODO handle asyncness if possible
@@ -121,15 +435,13 @@ fn function_from_decl(
     let syn::Signature { inputs, output, .. } = sig;
 
     // No handling of methods
-    let arguments = inputs
-        .into_iter()
-        .filter_map(|arg| match arg {
-            syn::FnArg::Typed(c) => Some(c),
-            syn::FnArg::Receiver(_) => {
-                panic!("arguments cannot be `self`")
-            }
-        })
-        .collect::<Vec<_>>();
+    let mut arguments = Vec::new();
+    for arg in inputs.into_iter() {
+        match arg {
+            syn::FnArg::Typed(c) => arguments.push(c),
+            syn::FnArg::Receiver(r) => bail_span!(r, "arguments cannot be `self`"),
+        }
+    }
 
     // Fetch return type
     let ret = match output {
@@ -142,6 +454,8 @@ fn function_from_decl(
         arguments,
         name: decl_name.to_string(),
         ret,
+        outputs: Vec::new(),
+        docs: String::new(),
     })
 }
 
@@ -153,40 +467,108 @@ pub(crate) trait MacroParse<Ctx> {
     fn macro_parse(self, program: &mut ast::Program, context: Ctx) -> Result<(), Diagnostic>;
 }
 
-impl<'a> MacroParse<&'a mut TokenStream> for syn::Item {
+impl<'a> MacroParse<(BindgenAttrs, &'a mut TokenStream)> for syn::Item {
     fn macro_parse(
         self,
         program: &mut ast::Program,
-        tokens: &'a mut TokenStream,
+        (opts, tokens): (BindgenAttrs, &'a mut TokenStream),
     ) -> Result<(), Diagnostic> {
+        // Skipped items are left untouched
+        if opts.skip.is_some() {
+            self.to_tokens(tokens);
+            return Ok(());
+        }
+
+        // Instantiations only make sense for generic functions, and are named on their own
+        if let (Some(instantiation), false) = (
+            opts.instantiations.first(),
+            matches!(self, syn::Item::Fn(_)),
+        ) {
+            bail_span!(
+                instantiation.keyword,
+                "only functions can be instantiated with #[holium_bindgen]"
+            );
+        }
+        if let (Some((keyword, _)), false) = (&opts.outputs, matches!(self, syn::Item::Fn(_))) {
+            bail_span!(
+                keyword,
+                "only the outputs of functions can be named with #[holium_bindgen]"
+            );
+        }
+        if let (Some(name), false) = (&opts.name, matches!(self, syn::Item::Fn(_))) {
+            bail_span!(
+                name,
+                "only functions can be exported under another name with #[holium_bindgen]"
+            );
+        }
+        if let (Some(name), Some(_)) = (&opts.name, opts.instantiations.first()) {
+            bail_span!(
+                name,
+                "instantiated functions are exported under the names of their instantiations"
+            );
+        }
+
         // Match of Item types to parse & generate our AST
         match self {
             // Handles public function
             syn::Item::Fn(f) => {
                 f.to_tokens(tokens);
 
-                let method_kind = ast::MethodKind::Operation(ast::Operation {
-                    is_static: true,
-                    kind: ast::OperationKind::Regular,
-                });
                 let rust_name = f.sig.ident.clone();
 
-                program.exports.push(ast::Export {
-                    function: f.convert()?,
-                    method_kind,
-                    rust_class: None,
-                    rust_name,
-                });
+                // Generic functions are exported once per instantiation, others once as they are
+                let functions = match opts.instantiations.is_empty() {
+                    true => vec![(f, vec![], opts.name)],
+                    false => {
+                        let mut functions = Vec::new();
+                        for instantiation in opts.instantiations.iter() {
+                            let (f, generics) = instantiation.apply(&f)?;
+                            functions.push((f, generics, Some(instantiation.name.clone())));
+                        }
+                        functions
+                    }
+                };
+
+                for (f, generics, name) in functions {
+                    let method_kind = ast::MethodKind::Operation(ast::Operation {
+                        is_static: true,
+                        kind: ast::OperationKind::Regular,
+                    });
+
+                    let mut function = f.convert()?;
+                    if let Some(name) = name {
+                        function.name = name.value();
+                    }
+                    if let Some((keyword, outputs)) = &opts.outputs {
+                        assert_tuple_output(&function, keyword, outputs)?;
+                        function.outputs = outputs.clone();
+                    }
+
+                    program.exports.push(ast::Export {
+                        function,
+                        method_kind,
+                        rust_class: None,
+                        rust_name: rust_name.clone(),
+                        generics,
+                    });
+                }
             }
             // Handles strcutures
             syn::Item::Struct(mut s) => {
-                program.structs.push((&mut s).convert()?);
+                let ast_struct = (&mut s).convert()?;
+                program.structs.push(ast_struct);
                 s.to_tokens(tokens);
             }
+            // Handles enums
+            syn::Item::Enum(mut e) => {
+                let ast_enum = (&mut e).convert()?;
+                program.enums.push(ast_enum);
+                e.to_tokens(tokens);
+            }
             _ => {
                 bail_span!(
                     self,
-                    "#[holium_bindgen] can only be applied to a public function or struct",
+                    "#[holium_bindgen] can only be applied to a public function, struct or enum",
                 );
             }
         }
@@ -195,6 +577,48 @@ impl<'a> MacroParse<&'a mut TokenStream> for syn::Item {
     }
 }
 
+/// Text of the doc comments among the given attributes, one line per attribute
+fn docs(attrs: &[syn::Attribute]) -> String {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(lit),
+                ..
+            })) => Some(lit.value()),
+            _ => None,
+        })
+        .map(|line| match line.strip_prefix(' ') {
+            Some(line) => line.to_owned(),
+            None => line,
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Check that named outputs match the positions of the tuple returned by the function.
+fn assert_tuple_output(
+    function: &ast::Function,
+    keyword: &syn::Ident,
+    outputs: &[syn::Ident],
+) -> Result<(), Diagnostic> {
+    match function.output_type() {
+        Some(syn::Type::Tuple(tuple)) if tuple.elems.len() == outputs.len() => Ok(()),
+        Some(syn::Type::Tuple(tuple)) => bail_span!(
+            keyword,
+            "{} output names given for a tuple of {} elements",
+            outputs.len(),
+            tuple.elems.len()
+        ),
+        Some(ty) => bail_span!(ty, "named outputs require the function to return a tuple"),
+        None => bail_span!(
+            keyword,
+            "named outputs require the function to return a tuple"
+        ),
+    }
+}
+
 /// Check there are no lifetimes on the function.
 fn assert_no_lifetimes(sig: &syn::Signature) -> Result<(), Diagnostic> {
     struct Walk {
@@ -204,7 +628,7 @@ fn assert_no_lifetimes(sig: &syn::Signature) -> Result<(), Diagnostic> {
     impl<'ast> syn::visit::Visit<'ast> for Walk {
         fn visit_lifetime(&mut self, i: &'ast syn::Lifetime) {
             self.diagnostics.push(err_span!(
-                &*i,
+                i,
                 "it is currently not sound to use lifetimes in function \
                  signatures"
             ));
@@ -0,0 +1,268 @@
+//! Serde attributes changing the serialized form of items tagged with `#[holium_bindgen]`. Key
+//! trees have to mirror this serialized form for data trees to be deserialized.
+
+use backend::Diagnostic;
+use syn::ext::IdentExt;
+
+/// Serde attributes of a container, a variant or a field
+#[derive(Default)]
+pub(crate) struct SerdeAttrs {
+    /// Name the item is deserialized from
+    pub(crate) rename: Option<String>,
+    /// Rule renaming the fields of a structure or the variants of an enum
+    pub(crate) rename_all: Option<RenameRule>,
+    /// Rule renaming the fields of all the struct variants of an enum
+    pub(crate) rename_all_fields: Option<RenameRule>,
+    /// Item is neither serialized nor deserialized
+    pub(crate) skip: bool,
+    /// Fields of the item are serialized inline in its parent structure
+    pub(crate) flatten: bool,
+    /// Item is serialized by custom functions, given through `with`, `serialize_with` or
+    /// `deserialize_with`
+    pub(crate) custom: bool,
+}
+
+impl SerdeAttrs {
+    /// Read the serde attributes among the given attributes. Attributes that do not change the
+    /// serialized form, like `bound`, are ignored, while attributes changing it in a way key trees
+    /// cannot mirror are rejected.
+    pub(crate) fn parse(attrs: &[syn::Attribute]) -> Result<Self, Diagnostic> {
+        let mut serde_attrs = SerdeAttrs::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+            let list = match attr.parse_meta()? {
+                syn::Meta::List(list) => list,
+                meta => bail_span!(meta, "expected #[serde(...)]"),
+            };
+
+            for nested in list.nested.iter() {
+                let meta = match nested {
+                    syn::NestedMeta::Meta(meta) => meta,
+                    syn::NestedMeta::Lit(lit) => {
+                        bail_span!(lit, "unexpected literal in serde attribute")
+                    }
+                };
+
+                match meta {
+                    syn::Meta::Path(path) if path.is_ident("skip") => serde_attrs.skip = true,
+                    syn::Meta::Path(path) if path.is_ident("flatten") => serde_attrs.flatten = true,
+                    syn::Meta::Path(path)
+                        if path.is_ident("skip_serializing")
+                            || path.is_ident("skip_deserializing") =>
+                    {
+                        bail_span!(
+                            path,
+                            "fields skipped in only one direction are not supported by \
+                             #[holium_bindgen], use `skip` instead"
+                        )
+                    }
+                    syn::Meta::Path(path) if path.is_ident("default") => bail_span!(
+                        path,
+                        "default values are not supported by #[holium_bindgen], as data is \
+                         positional and only trailing fields could be left out"
+                    ),
+                    syn::Meta::NameValue(name_value) if name_value.path.is_ident("default") => {
+                        bail_span!(
+                            name_value,
+                            "default values are not supported by #[holium_bindgen], as data is \
+                             positional and only trailing fields could be left out"
+                        )
+                    }
+                    syn::Meta::NameValue(name_value)
+                        if name_value.path.is_ident("tag")
+                            || name_value.path.is_ident("content") =>
+                    {
+                        bail_span!(
+                            name_value,
+                            "enums are adjacently tagged by #[holium_bindgen], their tag and \
+                             content keys cannot be changed"
+                        )
+                    }
+                    syn::Meta::Path(path) if path.is_ident("untagged") => bail_span!(
+                        path,
+                        "untagged enums are not supported by #[holium_bindgen], as their variant \
+                         is kept in data trees"
+                    ),
+                    syn::Meta::Path(path) if path.is_ident("transparent") => bail_span!(
+                        path,
+                        "transparent containers are not supported by #[holium_bindgen], newtype \
+                         structs are already serialized as their field"
+                    ),
+                    syn::Meta::NameValue(name_value)
+                        if name_value.path.is_ident("skip_serializing_if") =>
+                    {
+                        bail_span!(
+                            name_value,
+                            "conditionally skipped fields are not supported by #[holium_bindgen]"
+                        )
+                    }
+                    syn::Meta::NameValue(name_value)
+                        if ["with", "serialize_with", "deserialize_with"]
+                            .iter()
+                            .any(|key| name_value.path.is_ident(key)) =>
+                    {
+                        serde_attrs.custom = true
+                    }
+                    _ => {
+                        if let Some(rename) = deserialize_name(meta, "rename")? {
+                            serde_attrs.rename = Some(rename.value());
+                        }
+                        if let Some(rule) = deserialize_name(meta, "rename_all")? {
+                            serde_attrs.rename_all = Some(RenameRule::parse(&rule)?);
+                        }
+                        if let Some(rule) = deserialize_name(meta, "rename_all_fields")? {
+                            serde_attrs.rename_all_fields = Some(RenameRule::parse(&rule)?);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(serde_attrs)
+    }
+
+    /// Name a field is deserialized from, given the renaming rule of its container
+    pub(crate) fn field_name(&self, ident: &syn::Ident, rule: Option<RenameRule>) -> String {
+        let name = ident.unraw().to_string();
+        match (&self.rename, rule) {
+            (Some(rename), _) => rename.clone(),
+            (None, Some(rule)) => rule.apply_to_field(&name),
+            (None, None) => name,
+        }
+    }
+
+    /// Name a variant is deserialized from, given the renaming rule of its enum
+    pub(crate) fn variant_name(&self, ident: &syn::Ident, rule: Option<RenameRule>) -> String {
+        let name = ident.unraw().to_string();
+        match (&self.rename, rule) {
+            (Some(rename), _) => rename.clone(),
+            (None, Some(rule)) => rule.apply_to_variant(&name),
+            (None, None) => name,
+        }
+    }
+}
+
+/// Value of a `name = "..."` or `name(deserialize = "...")` serde attribute. As key trees are used
+/// to deserialize data trees, the deserialization name wins over the serialization one.
+fn deserialize_name(meta: &syn::Meta, name: &str) -> Result<Option<syn::LitStr>, Diagnostic> {
+    match meta {
+        syn::Meta::NameValue(name_value) if name_value.path.is_ident(name) => {
+            match &name_value.lit {
+                syn::Lit::Str(lit) => Ok(Some(lit.clone())),
+                lit => bail_span!(lit, "expected a string literal"),
+            }
+        }
+        syn::Meta::List(list) if list.path.is_ident(name) => {
+            let mut value = None;
+            for nested in list.nested.iter() {
+                match nested {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        path,
+                        lit: syn::Lit::Str(lit),
+                        ..
+                    })) if path.is_ident("deserialize") => return Ok(Some(lit.clone())),
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        path,
+                        lit: syn::Lit::Str(lit),
+                        ..
+                    })) if path.is_ident("serialize") => value = Some(lit.clone()),
+                    nested => bail_span!(nested, "expected `serialize` or `deserialize`"),
+                }
+            }
+            Ok(value)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Renaming rules of serde, applied to the fields of a structure or the variants of an enum
+#[derive(Clone, Copy)]
+pub(crate) enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &syn::LitStr) -> Result<Self, Diagnostic> {
+        Ok(match rule.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => bail_span!(rule, "unknown rename rule"),
+        })
+    }
+
+    /// Apply the rule to a variant name, written in PascalCase
+    pub(crate) fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_owned(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => {
+                let mut chars = variant.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            }
+            RenameRule::Snake => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            }
+            RenameRule::ScreamingSnake => RenameRule::Snake
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            RenameRule::Kebab => RenameRule::Snake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+            RenameRule::ScreamingKebab => RenameRule::ScreamingSnake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+
+    /// Apply the rule to a field name, written in snake_case
+    pub(crate) fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_owned(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Camel => {
+                RenameRule::Camel.apply_to_variant(&RenameRule::Pascal.apply_to_field(field))
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
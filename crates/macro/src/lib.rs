@@ -6,8 +6,8 @@ use proc_macro::TokenStream;
 use quote::quote;
 
 #[proc_macro_attribute]
-pub fn holium_bindgen(_attr: TokenStream, input: TokenStream) -> TokenStream {
-    match holium_macro_support::expand(input.into()) {
+pub fn holium_bindgen(attr: TokenStream, input: TokenStream) -> TokenStream {
+    match holium_macro_support::expand(attr.into(), input.into()) {
         Ok(tokens) => tokens.into(),
         Err(diagnostic) => (quote! { #diagnostic }).into(),
     }
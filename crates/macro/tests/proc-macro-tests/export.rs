@@ -1,5 +1,5 @@
 #![allow(unreachable_code)]
-use holium_rs_sdk::*;
+use holium_rs_sdk::holium_bindgen;
 use serde::{Deserialize, Serialize};
 
 #[holium_bindgen]
@@ -7,95 +7,186 @@ pub struct GoodStruct {
     number: u32,
 }
 
-#[holium_bindgen]
-pub fn pass1() {}
+struct BadStructNoMacro {
+    number: u32,
+}
 
 #[holium_bindgen]
-pub fn pass2() -> u32 {
-    0
+pub fn fail1(a: BadStructNoMacro) -> BadStructNoMacro {
+    a
+}
+
+#[derive(Serialize, Deserialize)]
+struct BadStructOnlySerde {
+    number: u32,
 }
 
 #[holium_bindgen]
-pub fn pass3(a: u32) -> u32 {
+pub fn fail2(a: BadStructOnlySerde) -> BadStructOnlySerde {
     a
 }
 
 #[holium_bindgen]
-pub fn pass4(a: u32) -> GoodStruct {
-    GoodStruct { number: a }
+pub fn fail3<'a>(x: &'a GoodStruct, y: &'a GoodStruct) -> &'a GoodStruct {
+    GoodStruct {
+        number: x.number + y.number,
+    }
 }
 
 #[holium_bindgen]
-pub fn pass5(a: GoodStruct) -> GoodStruct {
-    a
+pub fn fail4<T>(x: T) -> T {
+    x
 }
 
 #[holium_bindgen]
-pub fn pass6(a: &GoodStruct) -> GoodStruct {
-    GoodStruct { number: a.number }
+pub enum BadEnumLifetime<'a> {
+    Value(&'a str),
 }
 
 #[holium_bindgen]
-pub fn pass7(a: &mut GoodStruct) -> GoodStruct {
-    a.number += 10;
-    let new_struct = GoodStruct { number: a.number };
-    return new_struct;
+pub struct BadStructLifetime<'a> {
+    pub value: &'a str,
 }
 
-#[holium_bindgen]
-pub fn pass8(a: &mut GoodStruct) -> (GoodStruct, u32) {
-    let old_number = a.number;
-    a.number += 10;
-    let new_struct = GoodStruct { number: a.number };
-    return (new_struct, old_number);
+#[holium_bindgen(instantiate(T = u32))]
+pub fn fail6<T>(x: T) -> T {
+    x
 }
 
+#[holium_bindgen(instantiate(T = u32, U = u64, name = "fail7_u32"))]
+pub fn fail7<T>(x: T) -> T {
+    x
+}
+
+#[holium_bindgen(instantiate(name = "fail8_u32"))]
+pub fn fail8<T>(x: T) -> T {
+    x
+}
+
+#[holium_bindgen(instantiate(T = u32, name = "fail9_u32"))]
+pub struct BadStructInstantiated<T> {
+    pub value: T,
+}
+
+#[holium_bindgen(unknown)]
+pub fn fail10(x: u32) -> u32 {
+    x
+}
+
+pub struct BadErrorNoDisplay;
+
 #[holium_bindgen]
-pub fn pass9(a: &mut GoodStruct, b: String) -> (GoodStruct, String) {
-    a.number += 10;
-    let new_struct = GoodStruct { number: a.number };
-    return (new_struct, b);
+pub fn fail5(a: u32) -> Result<u32, BadErrorNoDisplay> {
+    Ok(a)
 }
 
 #[holium_bindgen]
-pub fn pass10(a: Option<u32>) -> Option<u32> {
-    a
+pub struct BadStructConditionalSkip {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<u32>,
 }
 
 #[holium_bindgen]
-pub fn pass11(a: Vec<u32>) -> Vec<u32> {
-    a
+#[serde(rename_all = "Title Case")]
+pub struct BadStructUnknownRenameRule {
+    pub number: u32,
 }
 
-struct BadStructNoMacro {
-    number: u32,
+#[holium_bindgen]
+pub struct BadStructDefaultField {
+    #[serde(default)]
+    pub first: u32,
+    pub second: u32,
 }
 
 #[holium_bindgen]
-pub fn fail1(a: BadStructNoMacro) -> BadStructNoMacro {
-    a
+#[serde(default = "BadStructDefault::new")]
+pub struct BadStructDefault {
+    pub number: u32,
 }
 
-#[derive(Serialize, Deserialize)]
-struct BadStructOnlySerde {
-    number: u32,
+#[holium_bindgen]
+#[serde(tag = "type")]
+pub enum BadEnumTag {
+    Value { number: u32 },
 }
 
 #[holium_bindgen]
-pub fn fail2(a: BadStructOnlySerde) -> BadStructOnlySerde {
-    a
+#[serde(content = "value")]
+pub enum BadEnumContent {
+    Value(u32),
 }
 
 #[holium_bindgen]
-pub fn fail3<'a>(x: &'a GoodStruct, y: &'a GoodStruct) -> &'a GoodStruct {
-    GoodStruct {
-        number: x.number + y.number,
-    }
+#[serde(untagged)]
+pub enum BadEnumUntagged {
+    Value(u32),
 }
 
 #[holium_bindgen]
-pub fn fail4<T>(x: T) -> T {
+#[serde(transparent)]
+pub struct BadStructTransparent {
+    pub number: u32,
+}
+
+#[holium_bindgen(name = "fail11", js_name = "fail11_alias")]
+pub fn fail11(x: u32) -> u32 {
+    x
+}
+
+#[holium_bindgen(name = "")]
+pub fn fail12(x: u32) -> u32 {
+    x
+}
+
+#[holium_bindgen(name = "fail13", instantiate(T = u32, name = "fail13_u32"))]
+pub fn fail13<T>(x: T) -> T {
+    x
+}
+
+#[holium_bindgen(name = fail14)]
+pub fn fail14(x: u32) -> u32 {
+    x
+}
+
+#[holium_bindgen(outputs(sum, count))]
+pub fn fail15(x: u32) -> (u32, u32, u32) {
+    (x, x, x)
+}
+
+#[holium_bindgen(outputs(sum))]
+pub fn fail16(x: u32) -> u32 {
     x
 }
 
+#[holium_bindgen(outputs(sum, sum))]
+pub fn fail17(x: u32) -> (u32, u32) {
+    (x, x)
+}
+
+#[holium_bindgen(outputs(value))]
+pub struct BadStructOutputs {
+    pub value: u32,
+}
+
+#[holium_bindgen(name = "Renamed")]
+pub struct BadStructRenamed {
+    pub value: u32,
+}
+
+#[holium_bindgen(js_name = "Renamed")]
+pub enum BadEnumRenamed {
+    Value(u32),
+}
+
+#[holium_bindgen]
+pub fn fail18(arg1: u32, _: u32) -> u32 {
+    arg1
+}
+
+#[holium_bindgen]
+pub fn fail19(path: String) -> std::io::Result<u32> {
+    Ok(path.len() as u32)
+}
+
 fn main() {}
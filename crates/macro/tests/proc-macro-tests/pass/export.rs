@@ -0,0 +1,398 @@
+#![allow(unreachable_code)]
+use holium_rs_sdk::holium_bindgen;
+use serde::Serialize;
+
+#[holium_bindgen]
+pub struct GoodStruct {
+    number: u32,
+}
+
+#[holium_bindgen]
+pub fn pass1() {}
+
+#[holium_bindgen]
+pub fn pass2() -> u32 {
+    0
+}
+
+#[holium_bindgen]
+pub fn pass3(a: u32) -> u32 {
+    a
+}
+
+#[holium_bindgen]
+pub fn pass4(a: u32) -> GoodStruct {
+    GoodStruct { number: a }
+}
+
+#[holium_bindgen]
+pub fn pass5(a: GoodStruct) -> GoodStruct {
+    a
+}
+
+#[holium_bindgen]
+pub fn pass6(a: &GoodStruct) -> GoodStruct {
+    GoodStruct { number: a.number }
+}
+
+#[holium_bindgen]
+pub fn pass7(a: &mut GoodStruct) -> GoodStruct {
+    a.number += 10;
+    let new_struct = GoodStruct { number: a.number };
+    return new_struct;
+}
+
+#[holium_bindgen]
+pub fn pass8(a: &mut GoodStruct) -> (GoodStruct, u32) {
+    let old_number = a.number;
+    a.number += 10;
+    let new_struct = GoodStruct { number: a.number };
+    return (new_struct, old_number);
+}
+
+#[holium_bindgen]
+pub fn pass9(a: &mut GoodStruct, b: String) -> (GoodStruct, String) {
+    a.number += 10;
+    let new_struct = GoodStruct { number: a.number };
+    return (new_struct, b);
+}
+
+#[holium_bindgen]
+pub fn pass10(a: Option<u32>) -> Option<u32> {
+    a
+}
+
+#[holium_bindgen]
+pub fn pass11(a: Vec<u32>) -> Vec<u32> {
+    a
+}
+
+#[derive(Debug, Serialize)]
+pub struct GoodError {
+    code: u32,
+}
+
+impl std::fmt::Display for GoodError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error code {}", self.code)
+    }
+}
+
+#[holium_bindgen]
+pub fn pass12(a: u32) -> Result<u32, GoodError> {
+    if a > 0 {
+        Ok(a)
+    } else {
+        Err(GoodError { code: a })
+    }
+}
+
+#[holium_bindgen]
+pub fn pass13(a: String) -> std::result::Result<u32, std::num::ParseIntError> {
+    a.parse()
+}
+
+#[holium_bindgen]
+pub fn pass13_core(a: String) -> core::result::Result<u32, std::num::ParseIntError> {
+    a.parse()
+}
+
+mod alias {
+    use holium_rs_sdk::holium_bindgen;
+
+    /// Type named `Result` that is not `std::result::Result`, serialized as a plain output
+    pub type Result<T> = Vec<T>;
+
+    #[holium_bindgen]
+    pub fn pass13_alias(a: u32) -> Result<u32> {
+        vec![a]
+    }
+}
+
+#[holium_bindgen]
+pub enum GoodUnitEnum {
+    First,
+    Second,
+}
+
+#[holium_bindgen]
+pub enum GoodEnum {
+    Circle { r: u32 },
+    Rect { w: u32, h: u32 },
+    Scaled(GoodStruct),
+    Pair(u32, u32),
+    Empty,
+}
+
+#[holium_bindgen]
+pub fn pass14(a: GoodUnitEnum, b: &GoodEnum) -> GoodEnum {
+    match (a, b) {
+        (GoodUnitEnum::First, GoodEnum::Circle { r }) => GoodEnum::Circle { r: *r },
+        _ => GoodEnum::Empty,
+    }
+}
+
+#[holium_bindgen]
+pub struct GoodStructWithMap {
+    pub counts: std::collections::BTreeMap<String, u64>,
+}
+
+#[holium_bindgen]
+pub fn pass15(
+    a: std::collections::BTreeMap<String, u64>,
+    b: GoodStructWithMap,
+) -> std::collections::HashMap<String, u64> {
+    a.into_iter().chain(b.counts.into_iter()).collect()
+}
+
+#[holium_bindgen]
+pub struct GoodStructWithTuple {
+    pub pair: (GoodStruct, Vec<GoodStructWithMap>),
+}
+
+#[holium_bindgen]
+pub fn pass16(a: (u32, String), b: GoodStructWithTuple) -> (GoodStruct, (u32, bool)) {
+    (b.pair.0, (a.0, a.1.is_empty()))
+}
+
+#[holium_bindgen]
+pub struct GoodRecursiveStruct {
+    pub value: std::num::NonZeroU32,
+    pub next: Option<Box<GoodRecursiveStruct>>,
+}
+
+#[holium_bindgen]
+pub enum GoodRecursiveEnum<T> {
+    Leaf(T),
+    Pair(Box<GoodRecursiveEnum<T>>, Box<Self>),
+    Branch { children: Vec<GoodRecursiveEnum<T>> },
+}
+
+#[holium_bindgen]
+pub fn pass17_enum(a: GoodRecursiveEnum<GoodRecursiveStruct>) -> GoodRecursiveEnum<u8> {
+    match a {
+        GoodRecursiveEnum::Leaf(_) => GoodRecursiveEnum::Leaf(0),
+        _ => GoodRecursiveEnum::Branch { children: vec![] },
+    }
+}
+
+#[holium_bindgen]
+pub fn pass17(a: GoodRecursiveStruct, b: std::num::Wrapping<u32>) -> Box<GoodRecursiveStruct> {
+    Box::new(GoodRecursiveStruct {
+        value: std::num::NonZeroU32::new(b.0).unwrap_or(a.value),
+        next: Some(Box::new(a)),
+    })
+}
+
+#[holium_bindgen]
+pub fn pass18(a: Vec<GoodStruct>, b: [GoodEnum; 2]) -> Vec<GoodStruct> {
+    a.into_iter().take(b.len()).collect()
+}
+
+#[holium_bindgen]
+pub struct GoodStructWithArrays {
+    pub signature: [u8; 64],
+    pub window: [f64; 1024],
+    #[serde(rename = "without_padding")]
+    pub unpadded: [u8; 48],
+}
+
+#[holium_bindgen]
+pub enum GoodEnumWithArrays {
+    Table { entries: [u8; 256] },
+    Signature([u8; 64], u32),
+}
+
+#[holium_bindgen]
+pub fn pass19(a: [u8; 256], b: &GoodStructWithArrays, c: GoodEnumWithArrays) -> [u8; 64] {
+    match c {
+        GoodEnumWithArrays::Signature(signature, _) => signature,
+        GoodEnumWithArrays::Table { .. } => [a[0]; 64],
+    }
+    .map(|byte| byte ^ b.signature[0])
+}
+
+#[holium_bindgen]
+pub fn pass20(a: [f64; 1024]) -> Result<[f64; 1024], GoodError> {
+    Ok(a)
+}
+
+#[holium_bindgen]
+pub struct GoodStructWithNestedArrays {
+    pub signatures: Vec<[u8; 64]>,
+    pub window: Option<[f64; 1024]>,
+    pub samples: (u32, [f64; 1024]),
+}
+
+#[holium_bindgen]
+pub enum GoodEnumWithNestedArrays {
+    Signatures { signatures: Vec<[u8; 64]> },
+    Window(Option<[f64; 1024]>, u32),
+}
+
+#[holium_bindgen]
+pub fn pass20_nested(
+    a: Vec<[u8; 64]>,
+    b: Option<[f64; 1024]>,
+    c: &GoodStructWithNestedArrays,
+    d: GoodEnumWithNestedArrays,
+) -> (u32, [f64; 1024]) {
+    match (b, d) {
+        (Some(window), _) | (_, GoodEnumWithNestedArrays::Window(Some(window), _)) => {
+            (a.len() as u32, window)
+        }
+        _ => c.samples,
+    }
+}
+
+#[holium_bindgen(outputs(signatures, window))]
+pub fn pass20_nested_outputs(a: Vec<[u8; 64]>) -> (Vec<[u8; 64]>, Option<[f64; 1024]>) {
+    (a, None)
+}
+
+#[holium_bindgen]
+#[serde(rename_all = "camelCase")]
+pub struct GoodStructWithSerdeAttributes {
+    pub first_field: u32,
+    #[serde(rename = "second")]
+    pub second_field: u32,
+    #[serde(flatten)]
+    pub inner: GoodStruct,
+    #[serde(skip)]
+    pub skipped: u32,
+    pub last_field: Vec<u32>,
+}
+
+#[holium_bindgen]
+#[serde(rename_all = "snake_case", rename_all_fields = "UPPERCASE")]
+pub enum GoodEnumWithSerdeAttributes {
+    FirstVariant { first_field: u32 },
+    #[serde(rename = "second", rename_all = "kebab-case")]
+    SecondVariant { second_field: u32 },
+    #[serde(skip)]
+    Skipped,
+}
+
+#[holium_bindgen]
+pub fn pass21(
+    a: GoodStructWithSerdeAttributes,
+    b: GoodEnumWithSerdeAttributes,
+) -> GoodEnumWithSerdeAttributes {
+    match b {
+        GoodEnumWithSerdeAttributes::FirstVariant { first_field } => {
+            GoodEnumWithSerdeAttributes::SecondVariant {
+                second_field: first_field + a.second_field,
+            }
+        }
+        b => b,
+    }
+}
+
+#[holium_bindgen]
+pub struct GoodStructWithPrivateFields {
+    pub visible: u32,
+    hidden: String,
+    pub(crate) internal: Vec<u32>,
+}
+
+#[holium_bindgen]
+pub fn pass22(a: GoodStructWithPrivateFields) -> GoodStructWithPrivateFields {
+    GoodStructWithPrivateFields {
+        visible: a.visible + a.internal.len() as u32,
+        hidden: a.hidden,
+        internal: vec![],
+    }
+}
+
+#[holium_bindgen]
+pub struct GoodNewtype(pub f64);
+
+#[holium_bindgen]
+pub struct GoodTupleStruct(pub f64, pub GoodStruct, Vec<GoodNewtype>);
+
+#[holium_bindgen]
+pub struct GoodUnitStruct;
+
+#[holium_bindgen]
+pub struct GoodStructWithTupleStructs {
+    pub meters: GoodNewtype,
+    pub unit: GoodUnitStruct,
+}
+
+#[holium_bindgen]
+pub fn pass23(a: GoodNewtype, b: GoodTupleStruct, c: GoodUnitStruct) -> GoodStructWithTupleStructs {
+    GoodStructWithTupleStructs {
+        meters: GoodNewtype(a.0 + b.0 + b.2.len() as f64),
+        unit: c,
+    }
+}
+
+#[holium_bindgen]
+pub struct GoodGenericStruct<T> {
+    pub first: T,
+    pub second: Vec<T>,
+}
+
+#[holium_bindgen]
+pub enum GoodGenericEnum<T, const N: usize> {
+    Value(T),
+    Values { values: [T; N] },
+}
+
+#[holium_bindgen(
+    instantiate(T = u32, name = "sum_u32"),
+    instantiate(T = f64, name = "sum_f64"),
+)]
+pub fn pass24<T: std::ops::Add<Output = T> + Copy>(
+    a: GoodGenericStruct<T>,
+    b: &GoodGenericEnum<T, 2>,
+) -> GoodGenericEnum<T, 1> {
+    let sum = a.second.iter().fold(a.first, |sum, value| sum + *value);
+    match b {
+        GoodGenericEnum::Value(value) => GoodGenericEnum::Value(sum + *value),
+        GoodGenericEnum::Values { values } => GoodGenericEnum::Values {
+            values: [sum + values[0]],
+        },
+    }
+}
+
+#[holium_bindgen(name = "main")]
+pub fn pass25(a: u32) -> u32 {
+    a
+}
+
+#[holium_bindgen(js_name = "sum-of-values")]
+pub fn pass26(a: Vec<u32>) -> u32 {
+    a.iter().sum()
+}
+
+#[holium_bindgen(name = "sum_of_values")]
+pub fn pass26_underscores(a: Vec<u32>) -> u32 {
+    a.iter().sum()
+}
+
+#[holium_bindgen(name = "0sum_2d_of_2d_values")]
+pub fn pass26_mangled(a: Vec<u32>) -> u32 {
+    a.iter().sum()
+}
+
+#[holium_bindgen(skip)]
+fn pass27<'a>(a: &'a str) -> &'a str {
+    a
+}
+
+/// Sum and count values, with a `"quoted"` type
+#[holium_bindgen(outputs(sum, count))]
+pub fn pass28(values: Vec<u32>, r#type: u32, _: bool) -> (u32, usize) {
+    (values.iter().sum::<u32>() + r#type, values.len())
+}
+
+#[holium_bindgen(outputs(r#min, max))]
+pub fn pass29((low, high): (u32, u32)) -> Result<(u32, u32), GoodError> {
+    match low <= high {
+        true => Ok((low, high)),
+        false => Err(GoodError { code: low }),
+    }
+}
+
+fn main() {}
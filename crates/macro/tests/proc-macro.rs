@@ -1,6 +1,7 @@
 #[test]
 fn proc_macro() {
     let t = trybuild::TestCases::new();
+    t.pass("tests/proc-macro-tests/pass/*.rs");
     t.compile_fail("tests/proc-macro-tests/*.rs");
 }
 
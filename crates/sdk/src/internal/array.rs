@@ -0,0 +1,384 @@
+//! Serde only implements its traits for arrays of up to 32 elements. This module allows to
+//! serialize and deserialize arrays of any length, and is applied by generated code to fields,
+//! arguments and outputs holding arrays.
+//!
+//! Fields whose type is an array are serialized through
+//! `#[serde(with = "holium_rs_sdk::internal::array")]`. Arrays nested in tuples and in standard
+//! containers, like `Vec<[u8; 64]>` or `Option<[f64; 1024]>`, are serialized through [`As`], with a
+//! type mirroring the one of the field in which arrays are kept and other types are replaced by
+//! [`Same`]: a `Vec<[u8; 64]>` is serialized with `As::<Vec<[Same; 64]>>`. Outputs holding arrays
+//! are wrapped in [`SerializedAs`] the same way.
+//!
+//! Arrays nested in other types, and arrays held by newtype enum variants, are still subject to the
+//! limit of serde.
+
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeTuple, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::TryInto;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// Serialize an array as a tuple of its elements, like serde does for short arrays
+pub fn serialize<S, T, const N: usize>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    <[Same; N]>::serialize_as(array, serializer)
+}
+
+/// Deserialize an array from a tuple of exactly `N` elements
+pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    <[Same; N]>::deserialize_as(deserializer)
+}
+
+/// Type serializing values of type `T` in its own way
+pub trait SerializeAs<T> {
+    fn serialize_as<S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error>;
+}
+
+/// Type deserializing values of type `T` in its own way
+pub trait DeserializeAs<'de, T> {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error>;
+}
+
+/// Values serialized and deserialized by serde itself
+pub struct Same;
+
+impl<T: Serialize> SerializeAs<T> for Same {
+    fn serialize_as<S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        value.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeAs<'de, T> for Same {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        T::deserialize(deserializer)
+    }
+}
+
+/// Serde `with` module serializing and deserializing values as `U`
+pub struct As<U>(PhantomData<U>);
+
+impl<U> As<U> {
+    pub fn serialize<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        U: SerializeAs<T>,
+    {
+        U::serialize_as(value, serializer)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        U: DeserializeAs<'de, T>,
+    {
+        U::deserialize_as(deserializer)
+    }
+}
+
+/// Wrapper serializing the value it holds as `U`
+pub struct SerializedAs<T, U>(T, PhantomData<U>);
+
+impl<T, U> SerializedAs<T, U> {
+    pub fn new(value: T) -> Self {
+        SerializedAs(value, PhantomData)
+    }
+}
+
+impl<T, U: SerializeAs<T>> Serialize for SerializedAs<T, U> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        U::serialize_as(&self.0, serializer)
+    }
+}
+
+/// Borrowed counterpart of [`SerializedAs`], for elements of containers
+struct SerializeAsRef<'a, T, U>(&'a T, PhantomData<U>);
+
+impl<'a, T, U> SerializeAsRef<'a, T, U> {
+    fn new(value: &'a T) -> Self {
+        SerializeAsRef(value, PhantomData)
+    }
+}
+
+impl<T, U: SerializeAs<T>> Serialize for SerializeAsRef<'_, T, U> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        U::serialize_as(self.0, serializer)
+    }
+}
+
+/// Value deserialized as `U`, for elements of containers
+struct DeserializedAs<T, U>(T, PhantomData<U>);
+
+impl<'de, T, U: DeserializeAs<'de, T>> Deserialize<'de> for DeserializedAs<T, U> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        U::deserialize_as(deserializer).map(|value| DeserializedAs(value, PhantomData))
+    }
+}
+
+impl<T, U: SerializeAs<T>, const N: usize> SerializeAs<[T; N]> for [U; N] {
+    fn serialize_as<S: Serializer>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for element in array {
+            tuple.serialize_element(&SerializeAsRef::<T, U>::new(element))?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, T, U: DeserializeAs<'de, T>, const N: usize> DeserializeAs<'de, [T; N]> for [U; N] {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<[T; N], D::Error> {
+        deserializer.deserialize_tuple(N, ArrayVisitor::<T, U, N>(PhantomData))
+    }
+}
+
+struct ArrayVisitor<T, U, const N: usize>(PhantomData<(T, U)>);
+
+impl<'de, T, U, const N: usize> Visitor<'de> for ArrayVisitor<T, U, N>
+where
+    U: DeserializeAs<'de, T>,
+{
+    type Value = [T; N];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an array of length {}", N)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[T; N], A::Error> {
+        let mut elements: Vec<T> = Vec::with_capacity(N);
+        while let Some(DeserializedAs(element, _)) = seq.next_element::<DeserializedAs<T, U>>()? {
+            if elements.len() == N {
+                return Err(de::Error::invalid_length(N + 1, &self));
+            }
+            elements.push(element);
+        }
+
+        let len = elements.len();
+        elements
+            .try_into()
+            .map_err(|_| de::Error::invalid_length(len, &self))
+    }
+}
+
+macro_rules! sequence_impls {
+    ($($ty:ident)+) => {
+        $(
+            impl<T, U: SerializeAs<T>> SerializeAs<$ty<T>> for $ty<U> {
+                fn serialize_as<S: Serializer>(value: &$ty<T>, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_seq(value.iter().map(SerializeAsRef::<T, U>::new))
+                }
+            }
+
+            impl<'de, T, U: DeserializeAs<'de, T>> DeserializeAs<'de, $ty<T>> for $ty<U> {
+                fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<$ty<T>, D::Error> {
+                    let elements = Vec::<DeserializedAs<T, U>>::deserialize(deserializer)?;
+                    Ok(elements.into_iter().map(|element| element.0).collect())
+                }
+            }
+        )+
+    }
+}
+
+sequence_impls! {
+    Vec
+    VecDeque
+}
+
+impl<T, U: SerializeAs<T>> SerializeAs<Option<T>> for Option<U> {
+    fn serialize_as<S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        value
+            .as_ref()
+            .map(SerializeAsRef::<T, U>::new)
+            .serialize(serializer)
+    }
+}
+
+impl<'de, T, U: DeserializeAs<'de, T>> DeserializeAs<'de, Option<T>> for Option<U> {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+        let value = Option::<DeserializedAs<T, U>>::deserialize(deserializer)?;
+        Ok(value.map(|value| value.0))
+    }
+}
+
+impl<T, U: SerializeAs<T>> SerializeAs<Box<T>> for Box<U> {
+    fn serialize_as<S: Serializer>(value: &Box<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        U::serialize_as(value, serializer)
+    }
+}
+
+impl<'de, T, U: DeserializeAs<'de, T>> DeserializeAs<'de, Box<T>> for Box<U> {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Box<T>, D::Error> {
+        U::deserialize_as(deserializer).map(Box::new)
+    }
+}
+
+macro_rules! map_impls {
+    ($($ty:ident <K $(: $bound1:ident $(+ $bound2:ident)*)?>)+) => {
+        $(
+            impl<K, V, KU, VU> SerializeAs<$ty<K, V>> for $ty<KU, VU>
+            where
+                KU: SerializeAs<K>,
+                VU: SerializeAs<V>,
+            {
+                fn serialize_as<S: Serializer>(value: &$ty<K, V>, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_map(value.iter().map(|(key, value)| {
+                        (SerializeAsRef::<K, KU>::new(key), SerializeAsRef::<V, VU>::new(value))
+                    }))
+                }
+            }
+
+            impl<'de, K, V, KU, VU> DeserializeAs<'de, $ty<K, V>> for $ty<KU, VU>
+            where
+                K: $($bound1 $(+ $bound2)*)?,
+                KU: DeserializeAs<'de, K>,
+                VU: DeserializeAs<'de, V>,
+            {
+                fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<$ty<K, V>, D::Error> {
+                    let entries = deserializer.deserialize_map(MapVisitor::<K, V, KU, VU>(PhantomData))?;
+                    Ok(entries.into_iter().collect())
+                }
+            }
+        )+
+    }
+}
+
+map_impls! {
+    BTreeMap<K: Ord>
+    HashMap<K: Eq + Hash>
+}
+
+/// Visitor reading the entries of a map, keys and values being deserialized as `KU` and `VU`
+struct MapVisitor<K, V, KU, VU>(PhantomData<(K, V, KU, VU)>);
+
+impl<'de, K, V, KU, VU> Visitor<'de> for MapVisitor<K, V, KU, VU>
+where
+    KU: DeserializeAs<'de, K>,
+    VU: DeserializeAs<'de, V>,
+{
+    type Value = Vec<(K, V)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Vec<(K, V)>, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) =
+            map.next_entry::<DeserializedAs<K, KU>, DeserializedAs<V, VU>>()?
+        {
+            entries.push((key.0, value.0));
+        }
+        Ok(entries)
+    }
+}
+
+macro_rules! tuple_impls {
+    ($($len:tt => ($($n:tt $t:ident $u:ident)+))+) => {
+        $(
+            impl<$($t, $u: SerializeAs<$t>),+> SerializeAs<($($t,)+)> for ($($u,)+) {
+                fn serialize_as<S: Serializer>(value: &($($t,)+), serializer: S) -> Result<S::Ok, S::Error> {
+                    let mut tuple = serializer.serialize_tuple($len)?;
+                    $(
+                        tuple.serialize_element(&SerializeAsRef::<$t, $u>::new(&value.$n))?;
+                    )+
+                    tuple.end()
+                }
+            }
+
+            impl<'de, $($t, $u: DeserializeAs<'de, $t>),+> DeserializeAs<'de, ($($t,)+)> for ($($u,)+) {
+                fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<($($t,)+), D::Error> {
+                    let value = <($(DeserializedAs<$t, $u>,)+)>::deserialize(deserializer)?;
+                    Ok(($(value.$n.0,)+))
+                }
+            }
+        )+
+    }
+}
+
+tuple_impls! {
+    1  => (0 T0 U0)
+    2  => (0 T0 U0 1 T1 U1)
+    3  => (0 T0 U0 1 T1 U1 2 T2 U2)
+    4  => (0 T0 U0 1 T1 U1 2 T2 U2 3 T3 U3)
+    5  => (0 T0 U0 1 T1 U1 2 T2 U2 3 T3 U3 4 T4 U4)
+    6  => (0 T0 U0 1 T1 U1 2 T2 U2 3 T3 U3 4 T4 U4 5 T5 U5)
+    7  => (0 T0 U0 1 T1 U1 2 T2 U2 3 T3 U3 4 T4 U4 5 T5 U5 6 T6 U6)
+    8  => (0 T0 U0 1 T1 U1 2 T2 U2 3 T3 U3 4 T4 U4 5 T5 U5 6 T6 U6 7 T7 U7)
+    9  => (0 T0 U0 1 T1 U1 2 T2 U2 3 T3 U3 4 T4 U4 5 T5 U5 6 T6 U6 7 T7 U7 8 T8 U8)
+    10 => (0 T0 U0 1 T1 U1 2 T2 U2 3 T3 U3 4 T4 U4 5 T5 U5 6 T6 U6 7 T7 U7 8 T8 U8 9 T9 U9)
+    11 => (0 T0 U0 1 T1 U1 2 T2 U2 3 T3 U3 4 T4 U4 5 T5 U5 6 T6 U6 7 T7 U7 8 T8 U8 9 T9 U9 10 T10 U10)
+    12 => (0 T0 U0 1 T1 U1 2 T2 U2 3 T3 U3 4 T4 U4 5 T5 U5 6 T6 U6 7 T7 U7 8 T8 U8 9 T9 U9 10 T10 U10 11 T11 U11)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Signature {
+        #[serde(with = "crate::internal::array")]
+        bytes: [u8; 64],
+    }
+
+    #[test]
+    fn can_round_trip_long_array() {
+        let mut signature = Signature { bytes: [0; 64] };
+        signature.bytes[63] = 1;
+
+        let bytes = serde_cbor::to_vec(&signature).unwrap();
+
+        assert_eq!(
+            serde_cbor::from_slice::<Signature>(&bytes).unwrap(),
+            signature
+        );
+        assert_eq!(
+            serde_cbor::to_vec(&SerializedAs::<_, [Same; 64]>::new([0u8; 64])).unwrap(),
+            serde_cbor::to_vec(&vec![0u8; 64]).unwrap()
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Nested {
+        #[serde(with = "As::<Vec<[Same; 64]>>")]
+        signatures: Vec<[u8; 64]>,
+        #[serde(with = "As::<Option<[Same; 1024]>>")]
+        window: Option<[f64; 1024]>,
+        #[serde(with = "As::<(Same, Box<[[Same; 40]; 2]>)>")]
+        pair: (u32, Box<[[u8; 40]; 2]>),
+        #[serde(with = "As::<BTreeMap<Same, [Same; 33]>>")]
+        keys: BTreeMap<String, [u8; 33]>,
+    }
+
+    #[test]
+    fn can_round_trip_nested_long_arrays() {
+        let nested = Nested {
+            signatures: vec![[1; 64], [2; 64]],
+            window: Some([0.5; 1024]),
+            pair: (3, Box::new([[4; 40], [5; 40]])),
+            keys: vec![(String::from("key"), [6; 33])].into_iter().collect(),
+        };
+
+        let bytes = serde_cbor::to_vec(&nested).unwrap();
+
+        assert_eq!(serde_cbor::from_slice::<Nested>(&bytes).unwrap(), nested);
+        assert_eq!(
+            serde_cbor::to_vec(&SerializedAs::<_, Vec<[Same; 64]>>::new(vec![[1u8; 64]])).unwrap(),
+            serde_cbor::to_vec(&vec![vec![1u8; 64]]).unwrap()
+        );
+    }
+
+    #[test]
+    fn cannot_deserialize_wrong_length() {
+        let bytes = serde_cbor::to_vec(&vec![0u8; 63]).unwrap();
+
+        assert!(serde_cbor::from_slice::<Signature>(&bytes).is_err());
+    }
+}
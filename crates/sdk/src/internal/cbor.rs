@@ -0,0 +1,284 @@
+//! Deterministic CBOR encoding, following the core deterministic encoding requirements of
+//! RFC 8949 (section 4.2), so that identical values are always encoded to identical bytes:
+//! - integers, lengths and tags use the shortest head possible ;
+//! - arrays, maps and strings are always encoded with a definite length ;
+//! - map entries are sorted by the bytewise lexicographic order of their encoded keys ;
+//! - floats are encoded in the shortest of the half, single and double precisions that represents
+//!   them exactly, and every NaN is encoded as the quiet NaN `0xf97e00`.
+//!
+//! Payloads exchanged between transformations and their host are encoded this way, which keeps
+//! outputs byte-identical across versions of the SDK and of `serde_cbor`.
+
+use serde::ser::Error as _;
+use serde::Serialize;
+use serde_cbor::Value as CborValue;
+use std::convert::TryFrom;
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+
+/// Encode a serializable value deterministically. Values are laid out as `serde_cbor` does, with
+/// structures as maps keyed by field names and enum variants keyed by their names.
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, serde_cbor::Error>
+where
+    T: Serialize + ?Sized,
+{
+    encode(&serde_cbor::value::to_value(value)?)
+}
+
+/// Encode a Cbor value deterministically
+pub fn encode(value: &CborValue) -> Result<Vec<u8>, serde_cbor::Error> {
+    let mut bytes = Vec::new();
+    encode_into(&mut bytes, value)?;
+    Ok(bytes)
+}
+
+fn encode_into(bytes: &mut Vec<u8>, value: &CborValue) -> Result<(), serde_cbor::Error> {
+    match value {
+        CborValue::Null => bytes.push(0xf6),
+        CborValue::Bool(v) => bytes.push(if *v { 0xf5 } else { 0xf4 }),
+        CborValue::Integer(v) => integer(bytes, *v)?,
+        CborValue::Float(v) => float(bytes, *v),
+        CborValue::Bytes(v) => {
+            head(bytes, MAJOR_BYTES, v.len() as u64);
+            bytes.extend_from_slice(v);
+        }
+        CborValue::Text(v) => {
+            head(bytes, MAJOR_TEXT, v.len() as u64);
+            bytes.extend_from_slice(v.as_bytes());
+        }
+        CborValue::Array(values) => {
+            head(bytes, MAJOR_ARRAY, values.len() as u64);
+            for value in values {
+                encode_into(bytes, value)?;
+            }
+        }
+        CborValue::Map(map) => {
+            let mut entries = map
+                .iter()
+                .map(|(key, value)| Ok((encode(key)?, value)))
+                .collect::<Result<Vec<_>, serde_cbor::Error>>()?;
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            head(bytes, MAJOR_MAP, entries.len() as u64);
+            for (key, value) in entries {
+                bytes.extend(key);
+                encode_into(bytes, value)?;
+            }
+        }
+        CborValue::Tag(tag, value) => {
+            head(bytes, MAJOR_TAG, *tag);
+            encode_into(bytes, value)?;
+        }
+        CborValue::__Hidden => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// Write the head of a data item, holding its major type and an argument in its shortest form
+pub(crate) fn head(bytes: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+    match argument {
+        0..=23 => bytes.push(major | argument as u8),
+        24..=0xff => bytes.extend_from_slice(&[major | 24, argument as u8]),
+        0x100..=0xffff => {
+            bytes.push(major | 25);
+            bytes.extend_from_slice(&(argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            bytes.push(major | 26);
+            bytes.extend_from_slice(&(argument as u32).to_be_bytes());
+        }
+        _ => {
+            bytes.push(major | 27);
+            bytes.extend_from_slice(&argument.to_be_bytes());
+        }
+    }
+}
+
+/// Write an integer, which has to fit the Cbor integer range `-2^64` to `2^64 - 1`
+pub(crate) fn integer(bytes: &mut Vec<u8>, v: i128) -> Result<(), serde_cbor::Error> {
+    let (major, argument) = match v {
+        v if v >= 0 => (MAJOR_UNSIGNED, u64::try_from(v)),
+        v => (MAJOR_NEGATIVE, u64::try_from(-1 - v)),
+    };
+    let argument = argument.map_err(|_| {
+        serde_cbor::Error::custom(format!("integer {} is out of the Cbor integer range", v))
+    })?;
+    head(bytes, major, argument);
+    Ok(())
+}
+
+fn float(bytes: &mut Vec<u8>, v: f64) {
+    if v.is_nan() {
+        bytes.extend_from_slice(&[0xf9, 0x7e, 0x00]);
+        return;
+    }
+
+    let single = v as f32;
+    if f64::from(single) != v {
+        bytes.push(0xfb);
+        bytes.extend_from_slice(&v.to_be_bytes());
+        return;
+    }
+    match half_bits(single) {
+        Some(half) => {
+            bytes.push(0xf9);
+            bytes.extend_from_slice(&half.to_be_bytes());
+        }
+        None => {
+            bytes.push(0xfa);
+            bytes.extend_from_slice(&single.to_be_bytes());
+        }
+    }
+}
+
+/// Bits of the half precision float equal to the given non-NaN single precision float, if any
+fn half_bits(v: f32) -> Option<u16> {
+    let bits = v.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127;
+    let mantissa = bits & 0x7f_ffff;
+
+    if v == 0.0 {
+        return Some(sign);
+    }
+    if v.is_infinite() {
+        return Some(sign | 0x7c00);
+    }
+    match exponent {
+        // Normal half precision float, whose mantissa only has 10 bits
+        -14..=15 if mantissa & 0x1fff == 0 => {
+            Some(sign | ((exponent + 15) as u16) << 10 | (mantissa >> 13) as u16)
+        }
+        // Subnormal half precision float, multiple of 2^-24
+        -24..=-15 => {
+            let shift = -exponent - 1;
+            let significand = 0x80_0000 | mantissa;
+            match significand & ((1 << shift) - 1) {
+                0 => Some(sign | (significand >> shift) as u16),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::data_tree::Node;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    fn assert_encodes_to(value: CborValue, hex: &str) {
+        let bytes = encode(&value).unwrap();
+        let encoded: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(encoded, hex, "encoding of {:?}", value);
+    }
+
+    #[test]
+    fn integers_use_shortest_head() {
+        for (v, hex) in [
+            (0, "00"),
+            (23, "17"),
+            (24, "1818"),
+            (1000, "1903e8"),
+            (1_000_000, "1a000f4240"),
+            (1_000_000_000_000, "1b000000e8d4a51000"),
+            (18_446_744_073_709_551_615, "1bffffffffffffffff"),
+            (-1, "20"),
+            (-1000, "3903e7"),
+            (-18_446_744_073_709_551_616, "3bffffffffffffffff"),
+        ] {
+            assert_encodes_to(CborValue::Integer(v), hex);
+        }
+        assert!(encode(&CborValue::Integer(1 << 64)).is_err());
+    }
+
+    #[test]
+    fn floats_use_shortest_exact_precision() {
+        for (v, hex) in [
+            (0.0, "f90000"),
+            (-0.0, "f98000"),
+            (1.5, "f93e00"),
+            (-4.0, "f9c400"),
+            (65504.0, "f97bff"),
+            (0.00006103515625, "f90400"),
+            (5.960464477539063e-8, "f90001"),
+            (100000.0, "fa47c35000"),
+            (3.4028234663852886e+38, "fa7f7fffff"),
+            (1.1, "fb3ff199999999999a"),
+            (1.0e+300, "fb7e37e43c8800759c"),
+            (f64::INFINITY, "f97c00"),
+            (f64::NEG_INFINITY, "f9fc00"),
+            (f64::NAN, "f97e00"),
+            (f64::from_bits(0xfff8_0000_0000_0001), "f97e00"),
+        ] {
+            assert_encodes_to(CborValue::Float(v), hex);
+        }
+    }
+
+    #[test]
+    fn map_keys_are_sorted_bytewise() {
+        let mut map = BTreeMap::new();
+        map.insert(CborValue::Text("aa".into()), CborValue::Integer(4));
+        map.insert(
+            CborValue::Array(vec![CborValue::Integer(100)]),
+            CborValue::Integer(5),
+        );
+        map.insert(CborValue::Integer(-1), CborValue::Integer(2));
+        map.insert(CborValue::Text("z".into()), CborValue::Integer(3));
+        map.insert(CborValue::Integer(10), CborValue::Integer(1));
+
+        assert_encodes_to(CborValue::Map(map), "a50a012002617a036261610481186405");
+    }
+
+    #[test]
+    fn can_encode_serializable() {
+        #[derive(Serialize)]
+        struct Structure {
+            zeta: u8,
+            alpha: Vec<&'static str>,
+        }
+
+        let bytes = to_vec(&Structure {
+            zeta: 1,
+            alpha: vec!["a"],
+        })
+        .unwrap();
+
+        assert_eq!(
+            bytes,
+            [
+                0xa2, 0x64, b'z', b'e', b't', b'a', 0x01, 0x65, b'a', b'l', b'p', b'h', b'a', 0x81,
+                0x61, b'a'
+            ]
+        );
+    }
+
+    #[test]
+    fn can_encode_data_tree() {
+        let node = Node::from_serializable(&true).unwrap();
+
+        assert_eq!(
+            node.encode().unwrap(),
+            [
+                &[0xa2, 0x65][..],
+                b"value",
+                &[0xa1, 0x64],
+                b"Bool",
+                &[0xf5, 0x68],
+                b"children",
+                &[0x80],
+            ]
+            .concat()
+        );
+    }
+}
@@ -1,17 +1,65 @@
 //! Data trees are responsible for recursively holding holium data. Leaves hold scalar CBOR values
 //! while non-leaf nodes point to ordered children.
+//!
+//! Floating-point leaves follow a deterministic policy so that a given input always results in
+//! byte-identical payloads:
+//! - half, single and double precision CBOR floats are all held as `f64`, and are encoded back
+//!   using the shortest of the three precisions that represents the value exactly ;
+//! - every NaN, whatever its sign or payload, is replaced by a single canonical quiet NaN,
+//!   encoded as `0xf97e00` ;
+//! - infinities and negative zero are preserved as is.
+//!
+//! Maps with dynamic keys are held by a non-leaf node with one child per entry, each entry being a
+//! non-leaf node whose first child holds the key and second child holds the value. As Cbor maps
+//! do not tell structures from maps, [`Node::new`] considers every Cbor map to be a structure and
+//! drops its keys, while [`Node::from_serializable`] keeps the keys of maps. Structures with
+//! flattened fields, which serde serializes as maps, are held the same way as maps: one entry per
+//! field, holding the name of the field and its value. This keeps the entries of flattened maps
+//! along with their keys. Entries are sorted by the deterministic encoding of their key node,
+//! so that equal maps result in byte-identical payloads whatever their iteration order.
+//!
+//! Homogeneous sequences are held by a non-leaf node with one child per item, the key node of
+//! their element being assigned to every item. The length of fixed-size arrays is checked against
+//! their key node.
+//!
+//! Tuples are held like sequences, by a non-leaf node with one child per element. Their key nodes
+//! name each element after its position, and are assigned back as Cbor arrays.
+//!
+//! Enumerations are held by a non-leaf node whose first child is a text leaf holding the name of
+//! the variant, followed by a second child holding the variant content if it carries any.
+//!
+//! Cbor tags, such as the ones of dates or bignums, are held by the node of the tagged value and
+//! assigned back around it. Rust values ask for a tag by serializing through a newtype structure
+//! named after [`TAG_NEWTYPE_PREFIX`], as the types of [`crate::types`] do.
 
-use crate::internal::key_tree::Node as KeyNode;
+use crate::internal::cbor;
+use crate::internal::error::{index_path, key_path, Error, ROOT_PATH};
+use crate::internal::key_tree::{GenerateNode, Node as KeyNode, Shape};
 use anyhow::Result;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_cbor::Value as CborValue;
 use std::collections::BTreeMap;
 
-#[derive(thiserror::Error, Debug)]
-enum Error {
-    #[error("float types are currently unhandled")]
-    FloatUnhandled,
-}
+#[cfg(feature = "cid")]
+mod dag_cbor;
+#[cfg(feature = "serde_json")]
+mod json;
+mod ser;
+
+#[cfg(feature = "cid")]
+pub use dag_cbor::{Cid, HashFunction, MerkleNode, DAG_CBOR_CODEC};
+
+/// Prefix of the name of the newtype structures through which a value asks to be tagged in a data
+/// tree, followed by the tag number. Other serializers handle such newtypes transparently.
+pub const TAG_NEWTYPE_PREFIX: &str = "\0holium_tag:";
+
+/// Key under which an enumeration serializes the name of its variant. Generated code tags enums
+/// with the same key.
+pub const ENUM_TAG: &str = "tag";
+/// Key under which an enumeration serializes the content of its variant. Generated code tags enums
+/// with the same key.
+pub const ENUM_CONTENT: &str = "content";
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 /// Value held by the leaf of a data tree
@@ -19,6 +67,7 @@ pub(crate) enum Value {
     Null,
     Bool(bool),
     Integer(i128),
+    Float(f64),
     Bytes(Vec<u8>),
     Text(String),
 }
@@ -29,17 +78,81 @@ impl Value {
             Value::Null => CborValue::Null,
             Value::Bool(v) => CborValue::Bool(*v),
             Value::Integer(v) => CborValue::Integer(*v),
+            Value::Float(v) => CborValue::Float(*v),
             Value::Bytes(v) => CborValue::Bytes(v.clone()),
             Value::Text(v) => CborValue::Text(v.clone()),
         }
     }
 }
 
+/// Tag number carried by the name of a newtype structure, if it starts with [`TAG_NEWTYPE_PREFIX`]
+fn newtype_tag(name: &str) -> Option<u64> {
+    name.strip_prefix(TAG_NEWTYPE_PREFIX)?.parse().ok()
+}
+
+/// Replace any NaN by a single canonical quiet NaN, leaving other values untouched
+fn canonical_float(v: f64) -> f64 {
+    if v.is_nan() {
+        f64::NAN
+    } else {
+        v
+    }
+}
+
+/// Key node of the field of a structure with flattened fields named after the given key, looking
+/// into flattened structures as well
+fn flattened_field<'a>(key_node: &'a KeyNode, key: &str) -> Option<&'a KeyNode> {
+    key_node
+        .children
+        .iter()
+        .find_map(|field| match (field.value, field.shape) {
+            (Some(name), _) if name == key => Some(field),
+            (None, Shape::Plain) | (None, Shape::Flattened) => flattened_field(field, key),
+            _ => None,
+        })
+}
+
+/// Key nodes of the keys and of the values of the map flattened in a structure, if any, which holds
+/// the entries not named after a field
+fn flattened_map(key_node: &KeyNode) -> Option<(&KeyNode, &KeyNode)> {
+    key_node
+        .children
+        .iter()
+        .find_map(|field| match (field.value, field.shape) {
+            (None, Shape::Map) => match field.children.as_slice() {
+                [keys_node, values_node] => Some((keys_node, values_node)),
+                _ => None,
+            },
+            (None, Shape::Plain) | (None, Shape::Flattened) => flattened_map(field),
+            _ => None,
+        })
+}
+
+/// Sort the entries of a map by the deterministic encoding of their key node, so that equal maps
+/// are held by identical nodes whatever the order their entries were inserted in
+fn sort_entries(entries: Vec<Node>) -> Result<Vec<Node>, serde_cbor::Error> {
+    let mut entries = entries
+        .into_iter()
+        .map(|entry| {
+            let key = match entry.children.first() {
+                Some(key) => key.encode()?,
+                None => vec![],
+            };
+            Ok((key, entry))
+        })
+        .collect::<Result<Vec<_>, serde_cbor::Error>>()?;
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 /// Recursive structure building simple data trees
 pub struct Node {
     pub(crate) value: Option<Value>,
     pub(crate) children: Vec<Node>,
+    /// Cbor tag of the value held by the node, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) tag: Option<u64>,
 }
 
 impl Node {
@@ -49,12 +162,14 @@ impl Node {
             Ok(Node {
                 value: Some(v),
                 children: vec![],
+                tag: None,
             })
         }
         fn new_non_leaf(children: Vec<Node>) -> Result<Node> {
             Ok(Node {
                 value: None,
                 children,
+                tag: None,
             })
         }
 
@@ -62,65 +177,532 @@ impl Node {
             CborValue::Null => new_leaf(Value::Null),
             CborValue::Bool(v) => new_leaf(Value::Bool(v)),
             CborValue::Integer(v) => new_leaf(Value::Integer(v)),
-            CborValue::Float(_) => Err(Error::FloatUnhandled.into()),
+            CborValue::Float(v) => new_leaf(Value::Float(canonical_float(v))),
             CborValue::Bytes(v) => new_leaf(Value::Bytes(v)),
             CborValue::Text(v) => new_leaf(Value::Text(v)),
-            CborValue::Tag(_, boxed_value) => Self::new(*boxed_value),
+            CborValue::Tag(tag, boxed_value) => {
+                Self::new(*boxed_value).map(|node| node.tagged(tag))
+            }
             CborValue::Array(values) => new_non_leaf(
                 values
                     .into_iter()
-                    .map(|v| Self::new(v))
+                    .map(Self::new)
                     .collect::<Result<Vec<Node>>>()?,
             ),
             CborValue::Map(tree_map) => new_non_leaf(
                 tree_map
                     .into_values()
-                    .map(|v| Self::new(v))
+                    .map(Self::new)
                     .collect::<Result<Vec<Node>>>()?,
             ),
             CborValue::__Hidden => unreachable!(),
         }
     }
 
+    /// Tag the node, unless it is already tagged. Only the innermost of nested tags is kept, the
+    /// outer ones usually being generic, such as the self-described Cbor tag.
+    fn tagged(self, tag: u64) -> Self {
+        Node {
+            tag: self.tag.or(Some(tag)),
+            ..self
+        }
+    }
+
+    /// Create a data tree from any serializable value. Contrary to going through a Cbor value, the
+    /// children of a structure are kept in the order its fields are serialized, which is the order
+    /// of the key tree generated for it, and map keys are kept as data.
+    pub fn from_serializable<T>(value: &T) -> Result<Self>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(value.serialize(ser::Serializer)?)
+    }
+
+    /// Deterministic Cbor encoding of the data tree, as exchanged between transformations and
+    /// their host
+    pub fn encode(&self) -> Result<Vec<u8>, serde_cbor::Error> {
+        cbor::to_vec(self)
+    }
+
     /// Fuse a key tree and a node tree to generate a Cbor structure based on them
-    pub fn assign_keys(&self, key_node: &KeyNode) -> CborValue {
+    pub fn assign_keys(&self, key_node: &KeyNode) -> Result<CborValue, Error> {
+        self.assign_keys_at(key_node, ROOT_PATH)
+    }
+
+    fn assign_keys_at(&self, key_node: &KeyNode, path: &str) -> Result<CborValue, Error> {
+        let value = self.assign_untagged_keys_at(key_node, path)?;
+        Ok(match self.tag {
+            Some(tag) => CborValue::Tag(tag, Box::new(value)),
+            None => value,
+        })
+    }
+
+    fn assign_untagged_keys_at(&self, key_node: &KeyNode, path: &str) -> Result<CborValue, Error> {
         match &self.value {
-            Some(value) => value.to_cbor(),
-            None => {
-                if &key_node.children.len() > &(0usize) {
-                    let mut map: BTreeMap<CborValue, CborValue> = BTreeMap::new();
-
-                    for (i, child) in self.children.iter().enumerate() {
-                        // todo We can unwrap here as we will handle the error more properly later on
-                        let key = &key_node.children.get(i).unwrap();
-                        map.insert(
-                            CborValue::Text(String::from(key.value.unwrap())),
-                            child.assign_keys(key),
-                        );
+            Some(value) => Ok(value.to_cbor()),
+            None => match key_node.shape {
+                Shape::Plain => {
+                    if !key_node.children.is_empty() {
+                        self.assign_field_keys(key_node, path)
+                    } else {
+                        let mut cbor_values: Vec<CborValue> = Vec::new();
+                        for (i, node) in self.children.iter().enumerate() {
+                            cbor_values.push(
+                                node.assign_keys_at(&KeyNode::default(), &index_path(path, i))?,
+                            );
+                        }
+                        Ok(CborValue::Array(cbor_values))
                     }
+                }
+                Shape::Sequence => self.assign_item_keys(key_node, path),
+                Shape::Array(len) => {
+                    if self.children.len() != len {
+                        return Err(Error::TypeMismatch {
+                            path: path.to_string(),
+                            message: format!(
+                                "expected an array of {} elements, found {}",
+                                len,
+                                self.children.len()
+                            ),
+                        });
+                    }
+                    self.assign_item_keys(key_node, path)
+                }
+                Shape::Enum => self.assign_variant_keys(key_node, path),
+                Shape::Map => self.assign_entry_keys(key_node, path),
+                Shape::Flattened => self.assign_flattened_keys(key_node, path),
+                Shape::Tuple => self.assign_element_keys(key_node, path),
+                // Key tree was cut to stop a recursion, generate the next level now that data
+                // is found under it
+                Shape::Recursive(reference) => {
+                    self.assign_untagged_keys_at(&(reference.generate)(), path)
+                }
+            },
+        }
+    }
+
+    /// Assign the keys of a structure fields to the children of the node
+    fn assign_field_keys(&self, key_node: &KeyNode, path: &str) -> Result<CborValue, Error> {
+        let mut map: BTreeMap<CborValue, CborValue> = BTreeMap::new();
+
+        for (i, child) in self.children.iter().enumerate() {
+            let key = match key_node.children.get(i) {
+                Some(KeyNode {
+                    value: Some(key), ..
+                }) => key,
+                _ => {
+                    return Err(Error::KeyTreeMismatch {
+                        path: index_path(path, i),
+                    })
+                }
+            };
+            map.insert(
+                CborValue::Text(String::from(*key)),
+                child.assign_keys_at(&key_node.children[i], &key_path(path, key))?,
+            );
+        }
+
+        Ok(CborValue::Map(map))
+    }
+
+    /// Assign the keys of the sequence element to every child of the node
+    fn assign_item_keys(&self, key_node: &KeyNode, path: &str) -> Result<CborValue, Error> {
+        let element_node = key_node
+            .children
+            .first()
+            .ok_or_else(|| Error::KeyTreeMismatch {
+                path: path.to_string(),
+            })?;
+
+        let mut cbor_values: Vec<CborValue> = Vec::new();
+        for (i, child) in self.children.iter().enumerate() {
+            cbor_values.push(child.assign_keys_at(element_node, &index_path(path, i))?);
+        }
+
+        Ok(CborValue::Array(cbor_values))
+    }
+
+    /// Assign the keys of each tuple element to the children of the node
+    fn assign_element_keys(&self, key_node: &KeyNode, path: &str) -> Result<CborValue, Error> {
+        let mut cbor_values: Vec<CborValue> = Vec::new();
+
+        for (i, child) in self.children.iter().enumerate() {
+            let element_path = index_path(path, i);
+            let element_node = key_node
+                .children
+                .get(i)
+                .ok_or_else(|| Error::KeyTreeMismatch {
+                    path: element_path.clone(),
+                })?;
+            cbor_values.push(child.assign_keys_at(element_node, &element_path)?);
+        }
 
-                    CborValue::Map(map)
-                } else {
-                    let mut cbor_values: Vec<CborValue> = Vec::new();
-                    for node in self.children.iter() {
-                        cbor_values.push(node.assign_keys(&KeyNode::default()));
+        Ok(CborValue::Array(cbor_values))
+    }
+
+    /// Rebuild a map from a node whose children are entries holding a key and a value
+    fn assign_entry_keys(&self, key_node: &KeyNode, path: &str) -> Result<CborValue, Error> {
+        let (keys_node, values_node) = match key_node.children.as_slice() {
+            [keys_node, values_node] => (keys_node, values_node),
+            _ => {
+                return Err(Error::KeyTreeMismatch {
+                    path: String::from(path),
+                })
+            }
+        };
+
+        let mut map: BTreeMap<CborValue, CborValue> = BTreeMap::new();
+
+        for (i, entry) in self.children.iter().enumerate() {
+            let entry_path = index_path(path, i);
+            match (&entry.value, entry.children.as_slice()) {
+                (None, [key, value]) => {
+                    map.insert(
+                        key.assign_keys_at(keys_node, &entry_path)?,
+                        value.assign_keys_at(values_node, &entry_path)?,
+                    );
+                }
+                _ => return Err(Error::KeyTreeMismatch { path: entry_path }),
+            }
+        }
+
+        Ok(CborValue::Map(map))
+    }
+
+    /// Rebuild a structure with flattened fields from a node whose children are entries holding a
+    /// key and a value. Entries named after a field are read as that field, other ones as entries
+    /// of the flattened map.
+    fn assign_flattened_keys(&self, key_node: &KeyNode, path: &str) -> Result<CborValue, Error> {
+        let mut map: BTreeMap<CborValue, CborValue> = BTreeMap::new();
+
+        for (i, entry) in self.children.iter().enumerate() {
+            let entry_path = index_path(path, i);
+            let (key, value) = match (&entry.value, entry.children.as_slice()) {
+                (None, [key, value]) => (key, value),
+                _ => return Err(Error::KeyTreeMismatch { path: entry_path }),
+            };
+
+            let field = match &key.value {
+                Some(Value::Text(name)) => {
+                    flattened_field(key_node, name).map(|field| (name, field))
+                }
+                _ => None,
+            };
+            match (field, flattened_map(key_node)) {
+                (Some((name, field)), _) => {
+                    map.insert(
+                        CborValue::Text(name.clone()),
+                        value.assign_keys_at(field, &key_path(path, name))?,
+                    );
+                }
+                (None, Some((keys_node, values_node))) => {
+                    map.insert(
+                        key.assign_keys_at(keys_node, &entry_path)?,
+                        value.assign_keys_at(values_node, &entry_path)?,
+                    );
+                }
+                (None, None) => return Err(Error::KeyTreeMismatch { path: entry_path }),
+            }
+        }
+
+        Ok(CborValue::Map(map))
+    }
+
+    /// Rebuild the adjacently tagged representation of an enumeration from a node holding the
+    /// variant name as a first child and the variant content, if any, as a second one
+    fn assign_variant_keys(&self, key_node: &KeyNode, path: &str) -> Result<CborValue, Error> {
+        let mismatch = || Error::KeyTreeMismatch {
+            path: String::from(path),
+        };
+
+        let (variant_name, content) = match self.children.as_slice() {
+            [Node {
+                value: Some(Value::Text(variant_name)),
+                ..
+            }] => (variant_name, None),
+            [Node {
+                value: Some(Value::Text(variant_name)),
+                ..
+            }, content] => (variant_name, Some(content)),
+            _ => return Err(mismatch()),
+        };
+        let variant_node = key_node
+            .children
+            .iter()
+            .find(|variant| variant.value == Some(variant_name.as_str()))
+            .ok_or_else(mismatch)?;
+
+        let mut map: BTreeMap<CborValue, CborValue> = BTreeMap::new();
+        map.insert(
+            CborValue::Text(String::from(ENUM_TAG)),
+            CborValue::Text(variant_name.clone()),
+        );
+        if let Some(content) = content {
+            map.insert(
+                CborValue::Text(String::from(ENUM_CONTENT)),
+                content.assign_keys_at(variant_node, &key_path(path, variant_name))?,
+            );
+        }
+
+        Ok(CborValue::Map(map))
+    }
+
+    /// Split a Cbor structure holding keys, as generated by [`assign_keys`](Node::assign_keys),
+    /// into a data tree following the given key tree. Structures may also be given as arrays of
+    /// their fields values, maps as arrays of `[key, value]` pairs, and unit enum variants as their
+    /// sole name.
+    pub fn strip_keys(value: CborValue, key_node: &KeyNode) -> Result<Self, Error> {
+        Self::strip_keys_at(value, key_node, ROOT_PATH)
+    }
+
+    fn strip_keys_at(value: CborValue, key_node: &KeyNode, path: &str) -> Result<Self, Error> {
+        let mismatch = || Error::KeyTreeMismatch {
+            path: String::from(path),
+        };
+        let non_leaf = |children| Node {
+            value: None,
+            children,
+            tag: None,
+        };
+
+        match (value, key_node.shape) {
+            (CborValue::Tag(tag, value), _) => {
+                Self::strip_keys_at(*value, key_node, path).map(|node| node.tagged(tag))
+            }
+            (value, Shape::Recursive(reference)) => {
+                Self::strip_keys_at(value, &(reference.generate)(), path)
+            }
+            (CborValue::Map(mut map), Shape::Plain) if !key_node.children.is_empty() => {
+                let mut children = Vec::new();
+                let mut omitted = None;
+                for field in key_node.children.iter() {
+                    let key = field.value.ok_or_else(mismatch)?;
+                    match (map.remove(&CborValue::Text(String::from(key))), omitted) {
+                        // Only trailing fields may be omitted, as data is positional
+                        (Some(_), Some(omitted)) => {
+                            return Err(Error::KeyTreeMismatch {
+                                path: key_path(path, omitted),
+                            })
+                        }
+                        (Some(value), None) => {
+                            children.push(Self::strip_keys_at(value, field, &key_path(path, key))?)
+                        }
+                        (None, _) => omitted = omitted.or(Some(key)),
                     }
-                    CborValue::Array(cbor_values)
+                }
+                match map.keys().next() {
+                    Some(CborValue::Text(key)) => Err(Error::KeyTreeMismatch {
+                        path: key_path(path, key),
+                    }),
+                    Some(_) => Err(mismatch()),
+                    None => Ok(non_leaf(children)),
                 }
             }
+            (CborValue::Array(values), Shape::Plain) if !key_node.children.is_empty() => {
+                Self::strip_element_keys(values, key_node, path).map(non_leaf)
+            }
+            (CborValue::Array(values), Shape::Tuple) => {
+                Self::strip_element_keys(values, key_node, path).map(non_leaf)
+            }
+            (CborValue::Array(values), Shape::Sequence)
+            | (CborValue::Array(values), Shape::Array(_)) => {
+                let element_node = key_node.children.first().ok_or_else(mismatch)?;
+                values
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        Self::strip_keys_at(value, element_node, &index_path(path, i))
+                    })
+                    .collect::<Result<Vec<Node>, Error>>()
+                    .map(non_leaf)
+            }
+            (CborValue::Map(map), Shape::Map) => {
+                let (keys_node, values_node) = match key_node.children.as_slice() {
+                    [keys_node, values_node] => (keys_node, values_node),
+                    _ => return Err(mismatch()),
+                };
+                let mut entries = Vec::new();
+                for (i, (key, value)) in map.into_iter().enumerate() {
+                    let entry_path = index_path(path, i);
+                    entries.push(non_leaf(vec![
+                        Self::strip_keys_at(key, keys_node, &entry_path)?,
+                        Self::strip_keys_at(value, values_node, &entry_path)?,
+                    ]));
+                }
+                sort_entries(entries)
+                    .map(non_leaf)
+                    .map_err(|e| Error::TypeMismatch {
+                        path: String::from(path),
+                        message: e.to_string(),
+                    })
+            }
+            (CborValue::Map(map), Shape::Flattened) => {
+                let mut entries = Vec::new();
+                for (i, (key, value)) in map.into_iter().enumerate() {
+                    let entry_path = index_path(path, i);
+                    let field = match &key {
+                        CborValue::Text(name) => flattened_field(key_node, name),
+                        _ => None,
+                    };
+                    let entry = match (field, key, flattened_map(key_node)) {
+                        (Some(field), CborValue::Text(name), _) => {
+                            let value = Self::strip_keys_at(value, field, &key_path(path, &name))?;
+                            let name = Node {
+                                value: Some(Value::Text(name)),
+                                children: vec![],
+                                tag: None,
+                            };
+                            vec![name, value]
+                        }
+                        (None, key, Some((keys_node, values_node))) => vec![
+                            Self::strip_keys_at(key, keys_node, &entry_path)?,
+                            Self::strip_keys_at(value, values_node, &entry_path)?,
+                        ],
+                        (_, CborValue::Text(name), None) => {
+                            return Err(Error::KeyTreeMismatch {
+                                path: key_path(path, &name),
+                            })
+                        }
+                        _ => return Err(Error::KeyTreeMismatch { path: entry_path }),
+                    };
+                    entries.push(non_leaf(entry));
+                }
+                sort_entries(entries)
+                    .map(non_leaf)
+                    .map_err(|e| Error::TypeMismatch {
+                        path: String::from(path),
+                        message: e.to_string(),
+                    })
+            }
+            (CborValue::Array(pairs), Shape::Map) => {
+                let (keys_node, values_node) = match key_node.children.as_slice() {
+                    [keys_node, values_node] => (keys_node, values_node),
+                    _ => return Err(mismatch()),
+                };
+                let mut entries = Vec::new();
+                for (i, pair) in pairs.into_iter().enumerate() {
+                    let entry_path = index_path(path, i);
+                    let (key, value) = match pair {
+                        CborValue::Array(pair) if pair.len() == 2 => {
+                            let mut pair = pair.into_iter();
+                            (pair.next().unwrap(), pair.next().unwrap())
+                        }
+                        _ => return Err(Error::KeyTreeMismatch { path: entry_path }),
+                    };
+                    entries.push(non_leaf(vec![
+                        Self::strip_keys_at(key, keys_node, &entry_path)?,
+                        Self::strip_keys_at(value, values_node, &entry_path)?,
+                    ]));
+                }
+                sort_entries(entries)
+                    .map(non_leaf)
+                    .map_err(|e| Error::TypeMismatch {
+                        path: String::from(path),
+                        message: e.to_string(),
+                    })
+            }
+            (CborValue::Text(variant_name), Shape::Enum) => Ok(non_leaf(vec![Node {
+                value: Some(Value::Text(variant_name)),
+                children: vec![],
+                tag: None,
+            }])),
+            (CborValue::Map(mut map), Shape::Enum) => {
+                let variant_name = match map.remove(&CborValue::Text(String::from(ENUM_TAG))) {
+                    Some(CborValue::Text(variant_name)) => variant_name,
+                    _ => return Err(mismatch()),
+                };
+                let variant_node = key_node
+                    .children
+                    .iter()
+                    .find(|variant| variant.value == Some(variant_name.as_str()))
+                    .ok_or_else(mismatch)?;
+
+                let content = map.remove(&CborValue::Text(String::from(ENUM_CONTENT)));
+                if !map.is_empty() {
+                    return Err(mismatch());
+                }
+                let mut children = vec![Node {
+                    value: Some(Value::Text(variant_name.clone())),
+                    children: vec![],
+                    tag: None,
+                }];
+                if let Some(content) = content {
+                    children.push(Self::strip_keys_at(
+                        content,
+                        variant_node,
+                        &key_path(path, &variant_name),
+                    )?);
+                }
+                Ok(non_leaf(children))
+            }
+            (value @ CborValue::Map(_), _) | (value @ CborValue::Array(_), Shape::Plain) => {
+                match key_node.children.is_empty() {
+                    true => Self::new(value).map_err(|e| Error::TypeMismatch {
+                        path: String::from(path),
+                        message: e.to_string(),
+                    }),
+                    false => Err(mismatch()),
+                }
+            }
+            (CborValue::Array(_), _) => Err(mismatch()),
+            (value, _) => Self::new(value).map_err(|e| Error::TypeMismatch {
+                path: String::from(path),
+                message: e.to_string(),
+            }),
         }
     }
+
+    /// Split each element of a tuple, or each field of a structure given as an array
+    fn strip_element_keys(
+        values: Vec<CborValue>,
+        key_node: &KeyNode,
+        path: &str,
+    ) -> Result<Vec<Node>, Error> {
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let element_path = index_path(path, i);
+                let element_node =
+                    key_node
+                        .children
+                        .get(i)
+                        .ok_or_else(|| Error::KeyTreeMismatch {
+                            path: element_path.clone(),
+                        })?;
+                Self::strip_keys_at(value, element_node, &element_path)
+            })
+            .collect()
+    }
+
+    /// Fuse the data tree with the key tree of a given type and deserialize the resulting Cbor
+    /// structure into that type
+    pub fn deserialize<T>(&self) -> Result<T, Error>
+    where
+        T: GenerateNode + DeserializeOwned,
+    {
+        let cbor = self.assign_keys(&T::generate_node())?;
+        let cbor_bytes = serde_cbor::to_vec(&cbor).map_err(|e| Error::TypeMismatch {
+            path: String::from(ROOT_PATH),
+            message: e.to_string(),
+        })?;
+
+        let mut deserializer = serde_cbor::Deserializer::from_slice(&cbor_bytes);
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|e| Error::TypeMismatch {
+            path: e.path().to_string(),
+            message: e.into_inner().to_string(),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use serde::Serialize;
+    use serde::{Deserialize, Serialize};
     use serde_cbor::value::to_value;
-    use std::collections::BTreeMap;
-
-    use crate::internal::key_tree::GenerateNode;
+    use std::collections::{BTreeMap, HashMap};
 
     use super::*;
+    use crate::internal::key_tree::{Recursion, Recursive, StaticNode};
 
     #[test]
     fn can_represent_null_value() {
@@ -128,7 +710,8 @@ mod tests {
             Node::new(CborValue::Null).unwrap(),
             Node {
                 value: Some(Value::Null),
-                children: vec![]
+                children: vec![],
+                tag: None,
             }
         )
     }
@@ -139,14 +722,16 @@ mod tests {
             Node::new(CborValue::from(true)).unwrap(),
             Node {
                 value: Some(Value::Bool(true)),
-                children: vec![]
+                children: vec![],
+                tag: None,
             }
         );
         assert_eq!(
             Node::new(CborValue::from(false)).unwrap(),
             Node {
                 value: Some(Value::Bool(false)),
-                children: vec![]
+                children: vec![],
+                tag: None,
             }
         )
     }
@@ -166,14 +751,18 @@ mod tests {
                         value: None,
                         children: vec![Node {
                             value: Some(Value::Null),
-                            children: vec![]
+                            children: vec![],
+                            tag: None,
                         }],
+                        tag: None,
                     },
                     Node {
                         value: Some(Value::Null),
-                        children: vec![]
+                        children: vec![],
+                        tag: None,
                     },
                 ],
+                tag: None,
             }
         )
     }
@@ -188,23 +777,167 @@ mod tests {
                 value: None,
                 children: vec![Node {
                     value: Some(Value::Integer(0)),
-                    children: vec![]
+                    children: vec![],
+                    tag: None,
                 },],
+                tag: None,
             }
         )
     }
 
     #[test]
     fn can_import_tagged_value() {
+        let tagged = CborValue::Tag(0, Box::from(CborValue::Null));
+        let node = Node::new(CborValue::Tag(55799, Box::from(tagged.clone()))).unwrap();
+
         assert_eq!(
-            Node::new(CborValue::Tag(0, Box::from(CborValue::Null),)).unwrap(),
+            node,
             Node {
                 value: Some(Value::Null),
-                children: vec![]
+                children: vec![],
+                tag: Some(0),
+            }
+        );
+        assert_eq!(node.assign_keys(&KeyNode::default()).unwrap(), tagged);
+        assert_eq!(Node::strip_keys(tagged, &KeyNode::default()).unwrap(), node);
+    }
+
+    #[test]
+    fn can_represent_float_of_any_precision() {
+        // half, single and double precision encodings of floats
+        let encoded_floats: Vec<(Vec<u8>, f64)> = vec![
+            (vec![0xf9, 0x3e, 0x00], 1.5),
+            (vec![0xfa, 0x47, 0xc3, 0x50, 0x00], 100000.0),
+            (
+                vec![0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a],
+                1.1,
+            ),
+        ];
+
+        for (bytes, float) in encoded_floats {
+            let cbor: CborValue = serde_cbor::from_slice(&bytes).unwrap();
+            assert_eq!(
+                Node::new(cbor).unwrap(),
+                Node {
+                    value: Some(Value::Float(float)),
+                    children: vec![],
+                    tag: None,
+                }
+            )
+        }
+    }
+
+    #[test]
+    fn nan_is_canonicalized() {
+        let negative_nan = f64::from_bits(0xfff8_0000_0000_0001);
+        let node = Node::new(CborValue::Float(negative_nan)).unwrap();
+        match node.value {
+            Some(Value::Float(v)) => assert_eq!(v.to_bits(), f64::NAN.to_bits()),
+            _ => panic!("expected a float leaf"),
+        }
+    }
+
+    #[test]
+    fn float_encoding_is_deterministic() {
+        let cases: Vec<(f64, Vec<u8>)> = vec![
+            (f64::NAN, vec![0xf9, 0x7e, 0x00]),
+            (
+                f64::from_bits(0x7ff0_0000_0000_0f00),
+                vec![0xf9, 0x7e, 0x00],
+            ),
+            (f64::INFINITY, vec![0xf9, 0x7c, 0x00]),
+            (f64::NEG_INFINITY, vec![0xf9, 0xfc, 0x00]),
+            (-0.0, vec![0xf9, 0x80, 0x00]),
+            (1.5, vec![0xf9, 0x3e, 0x00]),
+        ];
+
+        for (float, bytes) in cases {
+            let node = Node::new(CborValue::Float(float)).unwrap();
+            let value = node.value.unwrap().to_cbor();
+            assert_eq!(serde_cbor::to_vec(&value).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn can_represent_serializable_in_field_order() {
+        #[derive(Serialize)]
+        struct Structure {
+            second: u8,
+            first: Option<u8>,
+        }
+
+        assert_eq!(
+            Node::from_serializable(&Structure {
+                second: 0,
+                first: None
+            })
+            .unwrap(),
+            Node {
+                value: None,
+                children: vec![
+                    Node {
+                        value: Some(Value::Integer(0)),
+                        children: vec![],
+                        tag: None,
+                    },
+                    Node {
+                        value: Some(Value::Null),
+                        children: vec![],
+                        tag: None,
+                    },
+                ],
+                tag: None,
             }
         )
     }
 
+    #[test]
+    fn can_assign_float() {
+        #[derive(PartialEq, Serialize)]
+        struct Structure {
+            half: f32,
+            double: f64,
+            single: f32,
+        }
+
+        // This code is generated in the wasm module while compiling
+        impl GenerateNode for Structure {
+            const KEY_NODE: StaticNode = StaticNode {
+                children: &[
+                    StaticNode {
+                        value: Some("half"),
+                        ..f32::KEY_NODE
+                    },
+                    StaticNode {
+                        value: Some("double"),
+                        ..f64::KEY_NODE
+                    },
+                    StaticNode {
+                        value: Some("single"),
+                        ..f32::KEY_NODE
+                    },
+                ],
+                ..StaticNode::DEFAULT
+            };
+        }
+
+        let structure = Structure {
+            half: 0.5,
+            double: 0.1,
+            single: 0.1,
+        };
+
+        let structure_cbor = to_value(structure).unwrap();
+
+        let structure_data = Node::new(structure_cbor.clone()).unwrap();
+
+        let structure_assigned = structure_data
+            .assign_keys(&Structure::generate_node())
+            .unwrap();
+
+        assert_eq!(structure_cbor, structure_assigned);
+    }
+
     #[test]
     fn can_assign_map() {
         #[derive(Eq, PartialEq, Serialize)]
@@ -213,15 +946,13 @@ mod tests {
         }
         // This code is generated in the wasm module while compiling
         impl GenerateNode for Structure {
-            fn generate_node() -> KeyNode {
-                KeyNode {
-                    value: None,
-                    children: vec![KeyNode {
-                        value: Some("key"),
-                        children: NestedStructure::generate_node().children,
-                    }],
-                }
-            }
+            const KEY_NODE: StaticNode = StaticNode {
+                children: &[StaticNode {
+                    value: Some("key"),
+                    ..NestedStructure::KEY_NODE
+                }],
+                ..StaticNode::DEFAULT
+            };
         }
 
         #[derive(Eq, PartialEq, Serialize)]
@@ -231,15 +962,13 @@ mod tests {
 
         // This code is generated in the wasm module while compiling
         impl GenerateNode for NestedStructure {
-            fn generate_node() -> KeyNode {
-                KeyNode {
-                    value: None,
-                    children: vec![KeyNode {
-                        value: Some("key"),
-                        children: u8::generate_node().children,
-                    }],
-                }
-            }
+            const KEY_NODE: StaticNode = StaticNode {
+                children: &[StaticNode {
+                    value: Some("key"),
+                    ..u8::KEY_NODE
+                }],
+                ..StaticNode::DEFAULT
+            };
         }
 
         let structure = Structure {
@@ -250,7 +979,9 @@ mod tests {
 
         let structure_data = Node::new(structure_cbor.clone()).unwrap();
 
-        let structure_assigned = structure_data.assign_keys(&Structure::generate_node());
+        let structure_assigned = structure_data
+            .assign_keys(&Structure::generate_node())
+            .unwrap();
 
         assert_eq!(structure_cbor, structure_assigned);
     }
@@ -264,15 +995,13 @@ mod tests {
 
         // This code is generated in the wasm module while compiling
         impl GenerateNode for Structure {
-            fn generate_node() -> KeyNode {
-                KeyNode {
-                    value: None,
-                    children: vec![KeyNode {
-                        value: Some("key"),
-                        children: Vec::<u8>::generate_node().children,
-                    }],
-                }
-            }
+            const KEY_NODE: StaticNode = StaticNode {
+                children: &[StaticNode {
+                    value: Some("key"),
+                    ..Vec::<u8>::KEY_NODE
+                }],
+                ..StaticNode::DEFAULT
+            };
         }
 
         let structure = Structure {
@@ -283,8 +1012,605 @@ mod tests {
 
         let structure_data = Node::new(structure_cbor.clone()).unwrap();
 
-        let structure_assigned = structure_data.assign_keys(&Structure::generate_node());
+        let structure_assigned = structure_data
+            .assign_keys(&Structure::generate_node())
+            .unwrap();
 
         assert_eq!(structure_cbor, structure_assigned);
     }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Pair {
+        key: u8,
+        other: Vec<u8>,
+    }
+
+    // This code is generated in the wasm module while compiling
+    impl GenerateNode for Pair {
+        const KEY_NODE: StaticNode = StaticNode {
+            children: &[
+                StaticNode {
+                    value: Some("key"),
+                    ..u8::KEY_NODE
+                },
+                StaticNode {
+                    value: Some("other"),
+                    ..Vec::<u8>::KEY_NODE
+                },
+            ],
+            ..StaticNode::DEFAULT
+        };
+    }
+
+    #[test]
+    fn can_deserialize() {
+        let pair = Pair {
+            key: 0,
+            other: vec![1, 2],
+        };
+        let pair_data = Node::new(to_value(&pair).unwrap()).unwrap();
+
+        assert_eq!(pair_data.deserialize::<Pair>().unwrap(), pair);
+    }
+
+    #[test]
+    fn key_tree_mismatch_reports_path() {
+        let data = Node::new(CborValue::from(vec![
+            CborValue::Integer(0),
+            CborValue::from(vec![CborValue::Integer(1)]),
+            CborValue::Null,
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            data.deserialize::<Pair>().unwrap_err(),
+            Error::KeyTreeMismatch {
+                path: String::from("[2]")
+            }
+        );
+    }
+
+    #[test]
+    fn type_mismatch_reports_path() {
+        let data = Node::new(CborValue::from(vec![
+            CborValue::Integer(0),
+            CborValue::from(vec![CborValue::Integer(1), CborValue::Text("a".into())]),
+        ]))
+        .unwrap();
+
+        match data.deserialize::<Pair>().unwrap_err() {
+            Error::TypeMismatch { path, .. } => assert_eq!(path, "other[1]"),
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "tag", content = "content")]
+    enum Figure {
+        Point,
+        Circle { r: u32 },
+        Rect { w: u32, h: u32 },
+        Scaled(u32),
+    }
+
+    // This code is generated in the wasm module while compiling
+    impl GenerateNode for Figure {
+        const KEY_NODE: StaticNode = StaticNode {
+            children: &[
+                StaticNode {
+                    value: Some("Point"),
+                    ..StaticNode::DEFAULT
+                },
+                StaticNode {
+                    value: Some("Circle"),
+                    children: &[StaticNode {
+                        value: Some("r"),
+                        ..u32::KEY_NODE
+                    }],
+                    ..StaticNode::DEFAULT
+                },
+                StaticNode {
+                    value: Some("Rect"),
+                    children: &[
+                        StaticNode {
+                            value: Some("w"),
+                            ..u32::KEY_NODE
+                        },
+                        StaticNode {
+                            value: Some("h"),
+                            ..u32::KEY_NODE
+                        },
+                    ],
+                    ..StaticNode::DEFAULT
+                },
+                StaticNode {
+                    value: Some("Scaled"),
+                    ..u32::KEY_NODE
+                },
+            ],
+            shape: Shape::Enum,
+            ..StaticNode::DEFAULT
+        };
+    }
+
+    #[test]
+    fn can_assign_enum() {
+        for figure in [
+            Figure::Point,
+            Figure::Circle { r: 1 },
+            Figure::Rect { w: 2, h: 3 },
+            Figure::Scaled(4),
+        ] {
+            let figure_cbor = to_value(&figure).unwrap();
+            let figure_data = Node::from_serializable(&figure).unwrap();
+
+            assert_eq!(
+                figure_data.assign_keys(&Figure::generate_node()).unwrap(),
+                figure_cbor
+            );
+            assert_eq!(figure_data.deserialize::<Figure>().unwrap(), figure);
+        }
+    }
+
+    #[test]
+    fn unknown_variant_reports_path() {
+        let data = Node::new(CborValue::from(vec![CborValue::Text("Triangle".into())])).unwrap();
+
+        assert_eq!(
+            data.deserialize::<Figure>().unwrap_err(),
+            Error::KeyTreeMismatch {
+                path: String::from(ROOT_PATH)
+            }
+        );
+    }
+
+    #[test]
+    fn can_assign_map_with_dynamic_keys() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Structure {
+            counts: BTreeMap<String, u64>,
+            pairs: HashMap<u8, Pair>,
+        }
+
+        // This code is generated in the wasm module while compiling
+        impl GenerateNode for Structure {
+            const KEY_NODE: StaticNode = StaticNode {
+                children: &[
+                    StaticNode {
+                        value: Some("counts"),
+                        ..BTreeMap::<String, u64>::KEY_NODE
+                    },
+                    StaticNode {
+                        value: Some("pairs"),
+                        ..HashMap::<u8, Pair>::KEY_NODE
+                    },
+                ],
+                ..StaticNode::DEFAULT
+            };
+        }
+
+        let structure = Structure {
+            counts: vec![(String::from("b"), 1), (String::from("a"), 2)]
+                .into_iter()
+                .collect(),
+            pairs: vec![(
+                3,
+                Pair {
+                    key: 4,
+                    other: vec![5],
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+
+        let structure_data = Node::from_serializable(&structure).unwrap();
+        assert_eq!(
+            structure_data.children[0].children[0],
+            Node {
+                value: None,
+                children: vec![
+                    Node {
+                        value: Some(Value::Text(String::from("a"))),
+                        children: vec![],
+                        tag: None,
+                    },
+                    Node {
+                        value: Some(Value::Integer(2)),
+                        children: vec![],
+                        tag: None,
+                    }
+                ],
+                tag: None,
+            }
+        );
+
+        assert_eq!(
+            structure_data
+                .assign_keys(&Structure::generate_node())
+                .unwrap(),
+            to_value(&structure).unwrap()
+        );
+        assert_eq!(
+            structure_data.deserialize::<Structure>().unwrap(),
+            structure
+        );
+    }
+
+    #[test]
+    fn can_assign_tuple() {
+        type Tuple = (Pair, Vec<u8>, (u8, Pair));
+
+        let tuple: Tuple = (
+            Pair {
+                key: 0,
+                other: vec![1],
+            },
+            vec![2, 3],
+            (
+                4,
+                Pair {
+                    key: 5,
+                    other: vec![],
+                },
+            ),
+        );
+
+        let tuple_data = Node::from_serializable(&tuple).unwrap();
+
+        assert_eq!(
+            tuple_data.assign_keys(&Tuple::generate_node()).unwrap(),
+            to_value(&tuple).unwrap()
+        );
+        assert_eq!(tuple_data.deserialize::<Tuple>().unwrap(), tuple);
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Chain {
+        link: Box<u8>,
+        next: Option<Box<Chain>>,
+    }
+
+    // This code is generated in the wasm module while compiling
+    impl GenerateNode for Chain {
+        const KEY_NODE: StaticNode = StaticNode {
+            children: &[
+                StaticNode {
+                    value: Some("link"),
+                    ..Box::<u8>::KEY_NODE
+                },
+                StaticNode {
+                    value: Some("next"),
+                    ..Option::<Box<Recursion<Chain>>>::KEY_NODE
+                },
+            ],
+            type_name: Some(Chain::TYPE_NAME),
+            ..StaticNode::DEFAULT
+        };
+    }
+
+    impl Recursive for Chain {
+        const TYPE_NAME: &'static str = "data_tree::Chain";
+    }
+
+    #[test]
+    fn can_assign_recursive_type() {
+        let chain = Chain {
+            link: Box::new(0),
+            next: Some(Box::new(Chain {
+                link: Box::new(1),
+                next: Some(Box::new(Chain {
+                    link: Box::new(2),
+                    next: None,
+                })),
+            })),
+        };
+
+        let chain_data = Node::from_serializable(&chain).unwrap();
+
+        assert_eq!(
+            chain_data.assign_keys(&Chain::generate_node()).unwrap(),
+            to_value(&chain).unwrap()
+        );
+        assert_eq!(chain_data.deserialize::<Chain>().unwrap(), chain);
+    }
+
+    #[test]
+    fn can_assign_sequence_of_structures() {
+        let pairs = vec![
+            Pair {
+                key: 0,
+                other: vec![1, 2],
+            },
+            Pair {
+                key: 3,
+                other: vec![],
+            },
+        ];
+        let nested: [Vec<Pair>; 2] = [vec![], pairs];
+
+        let nested_data = Node::from_serializable(&nested).unwrap();
+
+        assert_eq!(
+            nested_data
+                .assign_keys(&<[Vec<Pair>; 2]>::generate_node())
+                .unwrap(),
+            to_value(&nested).unwrap()
+        );
+        assert_eq!(nested_data.deserialize::<[Vec<Pair>; 2]>().unwrap(), nested);
+    }
+
+    #[test]
+    fn cannot_assign_array_of_wrong_length() {
+        let data = Node::from_serializable(&vec![Some(vec![0u8; 63])]).unwrap();
+
+        assert_eq!(
+            data.assign_keys(&<(Option<[u8; 64]>,)>::generate_node()),
+            Err(Error::TypeMismatch {
+                path: "[0]".to_string(),
+                message: "expected an array of 64 elements, found 63".to_string(),
+            })
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Renamed {
+        first_key: u8,
+        #[serde(rename = "second")]
+        second_key: u8,
+        #[serde(flatten)]
+        pair: Pair,
+        #[serde(skip)]
+        skipped: u8,
+        #[serde(default)]
+        last_key: Vec<u8>,
+    }
+
+    // This code is generated in the wasm module while compiling
+    impl GenerateNode for Renamed {
+        const KEY_NODE: StaticNode = StaticNode {
+            shape: Shape::Flattened,
+            children: &[
+                StaticNode {
+                    value: Some("firstKey"),
+                    ..u8::KEY_NODE
+                },
+                StaticNode {
+                    value: Some("second"),
+                    ..u8::KEY_NODE
+                },
+                StaticNode { ..Pair::KEY_NODE },
+                StaticNode {
+                    value: Some("lastKey"),
+                    ..Vec::<u8>::KEY_NODE
+                },
+            ],
+            ..StaticNode::DEFAULT
+        };
+    }
+
+    #[test]
+    fn can_assign_serde_attributes() {
+        let renamed = Renamed {
+            first_key: 0,
+            second_key: 1,
+            pair: Pair {
+                key: 2,
+                other: vec![3],
+            },
+            skipped: 0,
+            last_key: vec![4],
+        };
+
+        let renamed_data = Node::from_serializable(&renamed).unwrap();
+
+        assert_eq!(renamed_data.children.len(), 5);
+        assert_eq!(
+            renamed_data.assign_keys(&Renamed::generate_node()).unwrap(),
+            to_value(&renamed).unwrap()
+        );
+        assert_eq!(renamed_data.deserialize::<Renamed>().unwrap(), renamed);
+
+        // Fields with a default value can be left out
+        let mut short_data = renamed_data;
+        short_data
+            .children
+            .retain(|entry| entry.children[0].value != Some(Value::Text(String::from("lastKey"))));
+        assert_eq!(
+            short_data.deserialize::<Renamed>().unwrap(),
+            Renamed {
+                last_key: vec![],
+                ..renamed
+            }
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Labeled {
+        id: u8,
+        #[serde(flatten)]
+        labels: HashMap<String, Vec<u8>>,
+    }
+
+    // This code is generated in the wasm module while compiling
+    impl GenerateNode for Labeled {
+        const KEY_NODE: StaticNode = StaticNode {
+            shape: Shape::Flattened,
+            children: &[
+                StaticNode {
+                    value: Some("id"),
+                    ..u8::KEY_NODE
+                },
+                StaticNode {
+                    ..HashMap::<String, Vec<u8>>::KEY_NODE
+                },
+            ],
+            ..StaticNode::DEFAULT
+        };
+    }
+
+    #[test]
+    fn can_assign_flattened_map() {
+        let labeled = Labeled {
+            id: 7,
+            labels: vec![
+                (String::from("red"), vec![1, 2]),
+                (String::from("id_like"), vec![]),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        let labeled_data = Node::from_serializable(&labeled).unwrap();
+        let cbor = labeled_data.assign_keys(&Labeled::generate_node()).unwrap();
+
+        assert_eq!(labeled_data.children.len(), 3);
+        assert_eq!(cbor, to_value(&labeled).unwrap());
+        assert_eq!(labeled_data.deserialize::<Labeled>().unwrap(), labeled);
+        assert_eq!(
+            Node::strip_keys(cbor, &Labeled::generate_node())
+                .unwrap()
+                .deserialize::<Labeled>()
+                .unwrap(),
+            labeled
+        );
+    }
+
+    #[test]
+    fn maps_of_unknown_length_keep_their_keys() {
+        struct Counts(Vec<(&'static str, u8)>);
+
+        impl Serialize for Counts {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::SerializeMap;
+                let mut map = serializer.serialize_map(None)?;
+                for (key, value) in self.0.iter() {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+
+        let counts_data = Node::from_serializable(&Counts(vec![("a", 1), ("b", 2)])).unwrap();
+
+        let mut expected = BTreeMap::new();
+        expected.insert(CborValue::Text(String::from("a")), CborValue::Integer(1));
+        expected.insert(CborValue::Text(String::from("b")), CborValue::Integer(2));
+        assert_eq!(
+            counts_data
+                .assign_keys(&HashMap::<String, u8>::generate_node())
+                .unwrap(),
+            CborValue::Map(expected)
+        );
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Meters(f64);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Segment(Meters, Pair, ());
+
+    // This code is generated in the wasm module while compiling
+    impl GenerateNode for Meters {
+        const KEY_NODE: StaticNode = f64::KEY_NODE;
+    }
+
+    // This code is generated in the wasm module while compiling
+    impl GenerateNode for Segment {
+        const KEY_NODE: StaticNode = StaticNode {
+            children: &[
+                StaticNode {
+                    value: Some("0"),
+                    ..Meters::KEY_NODE
+                },
+                StaticNode {
+                    value: Some("1"),
+                    ..Pair::KEY_NODE
+                },
+                StaticNode {
+                    value: Some("2"),
+                    ..<()>::KEY_NODE
+                },
+            ],
+            shape: Shape::Tuple,
+            ..StaticNode::DEFAULT
+        };
+    }
+
+    #[test]
+    fn can_assign_tuple_struct() {
+        let segment = Segment(
+            Meters(1.5),
+            Pair {
+                key: 0,
+                other: vec![1],
+            },
+            (),
+        );
+
+        let segment_data = Node::from_serializable(&segment).unwrap();
+
+        assert_eq!(
+            segment_data.assign_keys(&Segment::generate_node()).unwrap(),
+            to_value(&segment).unwrap()
+        );
+        assert_eq!(segment_data.deserialize::<Segment>().unwrap(), segment);
+    }
+
+    #[test]
+    fn can_strip_keys() {
+        let chain = Chain {
+            link: Box::new(0),
+            next: Some(Box::new(Chain {
+                link: Box::new(1),
+                next: None,
+            })),
+        };
+        assert_eq!(
+            Node::strip_keys(to_value(&chain).unwrap(), &Chain::generate_node()).unwrap(),
+            Node::from_serializable(&chain).unwrap()
+        );
+
+        for figure in [
+            Figure::Point,
+            Figure::Circle { r: 1 },
+            Figure::Rect { w: 2, h: 3 },
+            Figure::Scaled(4),
+        ] {
+            assert_eq!(
+                Node::strip_keys(to_value(&figure).unwrap(), &Figure::generate_node()).unwrap(),
+                Node::from_serializable(&figure).unwrap()
+            );
+        }
+        assert_eq!(
+            Node::strip_keys(CborValue::Text("Point".into()), &Figure::generate_node()).unwrap(),
+            Node::from_serializable(&Figure::Point).unwrap()
+        );
+
+        let mut map = HashMap::new();
+        map.insert(String::from("a"), vec![(1u8, 2.5f64)]);
+        assert_eq!(
+            Node::strip_keys(
+                to_value(&map).unwrap(),
+                &HashMap::<String, Vec<(u8, f64)>>::generate_node()
+            )
+            .unwrap(),
+            Node::from_serializable(&map).unwrap()
+        );
+    }
+
+    #[test]
+    fn cannot_strip_unknown_keys() {
+        let mut pair = BTreeMap::new();
+        pair.insert(CborValue::Text("key".into()), CborValue::Integer(0));
+        pair.insert(CborValue::Text("unknown".into()), CborValue::Integer(1));
+
+        assert_eq!(
+            Node::strip_keys(CborValue::Map(pair), &Pair::generate_node()).unwrap_err(),
+            Error::KeyTreeMismatch {
+                path: String::from("unknown")
+            }
+        );
+    }
 }
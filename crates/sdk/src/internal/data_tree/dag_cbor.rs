@@ -0,0 +1,420 @@
+//! Content addressing of data trees, following the IPLD data model.
+//!
+//! Every non-leaf node of a data tree is encoded as a DAG-CBOR block holding an array of its
+//! children. Leaf children are inlined as scalar values, while non-leaf children are replaced by a
+//! link to their own block: a CID under Cbor tag 42. The CID of a node is thus computed from the
+//! CIDs of its subtrees, Merkle-style, so that any subtree can be identified, deduplicated or
+//! verified on its own. A leaf node on its own is encoded as a block holding its scalar value.
+//!
+//! Blocks follow the DAG-CBOR canonical form:
+//! - integers and lengths use the shortest head possible ;
+//! - floats are always encoded in double precision. NaN and infinities are not allowed by
+//!   DAG-CBOR, so data trees holding them cannot be content-addressed ;
+//! - integers must fit the Cbor integer range, `-2^64` to `2^64 - 1` ;
+//! - tag 42 is the only Cbor tag allowed by DAG-CBOR. Byte strings under tag 42 are kept as
+//!   links, as long as they hold a CID behind the `0x00` multibase prefix. Values under any other
+//!   tag are encoded as the pair `[tag, value]`, so that dates or bignums can be content-addressed
+//!   too. A tagged value thus shares its CID with the pair of its tag and untagged value.
+//!
+//! CIDs are version 1, with the DAG-CBOR codec and either a sha2-256 or a blake3 multihash.
+
+use super::{Node, Value};
+use crate::internal::cbor::{head, integer};
+use crate::internal::error::{index_path, Error, ROOT_PATH};
+use sha2::Digest;
+use std::fmt;
+
+/// Multicodec code of DAG-CBOR
+pub const DAG_CBOR_CODEC: u64 = 0x71;
+
+/// Cbor tag of IPLD links
+const LINK_TAG: u64 = 42;
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_TAG: u8 = 6;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Hash function used to compute CIDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashFunction {
+    Sha2_256,
+    Blake3,
+}
+
+impl HashFunction {
+    /// Multicodec code of the hash function
+    pub fn code(self) -> u64 {
+        match self {
+            HashFunction::Sha2_256 => 0x12,
+            HashFunction::Blake3 => 0x1e,
+        }
+    }
+
+    fn digest(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            HashFunction::Sha2_256 => sha2::Sha256::digest(bytes).to_vec(),
+            HashFunction::Blake3 => blake3::hash(bytes).as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Version 1 content identifier of a DAG-CBOR block
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cid {
+    hash: HashFunction,
+    digest: Vec<u8>,
+}
+
+impl Cid {
+    /// CID of the given DAG-CBOR block
+    pub fn new(hash: HashFunction, block: &[u8]) -> Self {
+        Cid {
+            hash,
+            digest: hash.digest(block),
+        }
+    }
+
+    pub fn hash_function(&self) -> HashFunction {
+        self.hash
+    }
+
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Binary form of the CID: its version, codec and multihash
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.digest.len() + 4);
+        for value in [
+            1,
+            DAG_CBOR_CODEC,
+            self.hash.code(),
+            self.digest.len() as u64,
+        ] {
+            varint(&mut bytes, value);
+        }
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+}
+
+impl fmt::Display for Cid {
+    /// Multibase form of the CID, in lowercase base32
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.to_bytes();
+        let mut encoded = String::with_capacity(1 + (bytes.len() * 8).div_ceil(5));
+        encoded.push('b');
+        for chunk in bytes.chunks(5) {
+            let group = chunk
+                .iter()
+                .enumerate()
+                .fold(0u64, |group, (i, &b)| group | u64::from(b) << (32 - 8 * i));
+            for i in 0..(chunk.len() * 8).div_ceil(5) {
+                encoded.push(char::from(
+                    BASE32_ALPHABET[(group >> (35 - 5 * i) & 0x1f) as usize],
+                ));
+            }
+        }
+        f.write_str(&encoded)
+    }
+}
+
+/// CIDs of a node and of all its descendants, mirroring the shape of the data tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleNode {
+    pub cid: Cid,
+    pub children: Vec<MerkleNode>,
+}
+
+impl Node {
+    /// CID of the node, computed over its DAG-CBOR block
+    pub fn cid(&self, hash: HashFunction) -> Result<Cid, Error> {
+        Ok(self.merkle_tree(hash)?.cid)
+    }
+
+    /// DAG-CBOR block of the node, in which non-leaf children are linked by their CID
+    pub fn block(&self, hash: HashFunction) -> Result<Vec<u8>, Error> {
+        let merkle_node = self.merkle_tree(hash)?;
+        self.encode_block(&merkle_node.children, ROOT_PATH)
+    }
+
+    /// CIDs of the node and of all its descendants, each block being hashed only once
+    pub fn merkle_tree(&self, hash: HashFunction) -> Result<MerkleNode, Error> {
+        self.merkle_tree_at(hash, ROOT_PATH)
+    }
+
+    fn merkle_tree_at(&self, hash: HashFunction, path: &str) -> Result<MerkleNode, Error> {
+        let children = self
+            .children
+            .iter()
+            .enumerate()
+            .map(|(i, child)| child.merkle_tree_at(hash, &index_path(path, i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let block = self.encode_block(&children, path)?;
+
+        Ok(MerkleNode {
+            cid: Cid::new(hash, &block),
+            children,
+        })
+    }
+
+    fn encode_block(&self, children: &[MerkleNode], path: &str) -> Result<Vec<u8>, Error> {
+        let mut block = Vec::new();
+        match &self.value {
+            Some(value) => encode_leaf(&mut block, self.tag, value, path)?,
+            None => {
+                encode_tag(&mut block, self.tag, path)?;
+                head(&mut block, MAJOR_ARRAY, self.children.len() as u64);
+                for (i, (child, merkle_node)) in self.children.iter().zip(children).enumerate() {
+                    match &child.value {
+                        Some(value) => {
+                            encode_leaf(&mut block, child.tag, value, &index_path(path, i))?
+                        }
+                        None => {
+                            let mut link = vec![0];
+                            link.extend(merkle_node.cid.to_bytes());
+                            head(&mut block, MAJOR_TAG, LINK_TAG);
+                            head(&mut block, MAJOR_BYTES, link.len() as u64);
+                            block.extend(link);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(block)
+    }
+}
+
+/// Write a leaf value, along with its tag if any
+fn encode_leaf(
+    block: &mut Vec<u8>,
+    tag: Option<u64>,
+    value: &Value,
+    path: &str,
+) -> Result<(), Error> {
+    match (tag, value) {
+        (Some(LINK_TAG), Value::Bytes(cid)) if cid.first() == Some(&0) => {
+            head(block, MAJOR_TAG, LINK_TAG)
+        }
+        (tag, _) => encode_tag(block, tag, path)?,
+    }
+    encode_value(block, value, path)
+}
+
+/// Write the head of the `[tag, value]` pair standing for a tagged value, which is to be followed
+/// by the untagged value
+fn encode_tag(block: &mut Vec<u8>, tag: Option<u64>, path: &str) -> Result<(), Error> {
+    match tag {
+        None => {}
+        Some(LINK_TAG) => {
+            return Err(Error::TypeMismatch {
+                path: String::from(path),
+                message: String::from("Cbor tag 42 has to hold a CID in a byte string"),
+            })
+        }
+        Some(tag) => {
+            head(block, MAJOR_ARRAY, 2);
+            head(block, MAJOR_UNSIGNED, tag);
+        }
+    }
+
+    Ok(())
+}
+
+fn encode_value(block: &mut Vec<u8>, value: &Value, path: &str) -> Result<(), Error> {
+    let out_of_range = |message: String| Error::TypeMismatch {
+        path: String::from(path),
+        message,
+    };
+
+    match value {
+        Value::Null => block.push(0xf6),
+        Value::Bool(v) => block.push(if *v { 0xf5 } else { 0xf4 }),
+        Value::Integer(v) => integer(block, *v).map_err(|e| out_of_range(e.to_string()))?,
+        Value::Float(v) if v.is_finite() => {
+            block.push(0xfb);
+            block.extend_from_slice(&v.to_be_bytes());
+        }
+        Value::Float(v) => {
+            return Err(out_of_range(format!(
+                "{} cannot be represented in DAG-CBOR",
+                v
+            )))
+        }
+        Value::Bytes(v) => {
+            head(block, MAJOR_BYTES, v.len() as u64);
+            block.extend_from_slice(v);
+        }
+        Value::Text(v) => {
+            head(block, MAJOR_TEXT, v.len() as u64);
+            block.extend_from_slice(v.as_bytes());
+        }
+    }
+
+    Ok(())
+}
+
+/// Write an unsigned LEB128 varint, as used by multiformats
+fn varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Timestamp;
+    use serde_cbor::Value as CborValue;
+    use std::collections::HashMap;
+
+    #[test]
+    fn can_encode_blocks() {
+        let node = Node::from_serializable(&(1u8, "a", vec![true], -500i32, 0.5f32)).unwrap();
+
+        let block = node.block(HashFunction::Sha2_256).unwrap();
+
+        let sequence_cid = node.children[2].cid(HashFunction::Sha2_256).unwrap();
+        assert_eq!(
+            node.children[2].block(HashFunction::Sha2_256).unwrap(),
+            [0x81, 0xf5]
+        );
+        assert_eq!(
+            sequence_cid.to_string(),
+            "bafyreiece3u4dyrsed45bpjrqcfavtkirgksawtforctaht6johgtsobwq"
+        );
+        assert_eq!(
+            block,
+            [
+                &[0x85, 0x01, 0x61, 0x61, 0xd8, 0x2a, 0x58, 0x25, 0x00][..],
+                &sequence_cid.to_bytes(),
+                &[0x39, 0x01, 0xf3, 0xfb, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0],
+            ]
+            .concat()
+        );
+        assert_eq!(
+            node.cid(HashFunction::Sha2_256).unwrap(),
+            Cid::new(HashFunction::Sha2_256, &block)
+        );
+    }
+
+    #[test]
+    fn can_compute_merkle_tree() {
+        let node = Node::from_serializable(&(vec![1u8, 2], vec![1u8, 2], vec![3u8])).unwrap();
+
+        let merkle_tree = node.merkle_tree(HashFunction::Blake3).unwrap();
+
+        assert_eq!(merkle_tree.cid, node.cid(HashFunction::Blake3).unwrap());
+        assert_eq!(merkle_tree.cid.hash_function(), HashFunction::Blake3);
+        assert_eq!(&merkle_tree.cid.to_bytes()[..4], &[0x01, 0x71, 0x1e, 0x20]);
+        assert_eq!(merkle_tree.children[0].cid, merkle_tree.children[1].cid);
+        assert_ne!(merkle_tree.children[0].cid, merkle_tree.children[2].cid);
+        assert_eq!(
+            merkle_tree.children[2].cid,
+            node.children[2].cid(HashFunction::Blake3).unwrap()
+        );
+        assert_ne!(merkle_tree.cid, node.cid(HashFunction::Sha2_256).unwrap());
+    }
+
+    #[test]
+    fn equal_maps_have_equal_cids() {
+        let keys: Vec<String> = (0..32).map(|i| format!("key{}", i)).collect();
+        let forward: HashMap<&str, usize> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k.as_str(), i))
+            .collect();
+        let backward: HashMap<&str, usize> = keys
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, k)| (k.as_str(), i))
+            .collect();
+
+        let forward = Node::from_serializable(&(forward,)).unwrap();
+        let backward = Node::from_serializable(&(backward,)).unwrap();
+
+        assert_eq!(forward, backward);
+        assert_eq!(forward.encode().unwrap(), backward.encode().unwrap());
+        assert_eq!(
+            forward.cid(HashFunction::Sha2_256).unwrap(),
+            backward.cid(HashFunction::Sha2_256).unwrap()
+        );
+    }
+
+    #[test]
+    fn cannot_address_non_finite_floats() {
+        let node = Node::from_serializable(&(vec![f64::INFINITY],)).unwrap();
+
+        assert!(matches!(
+            node.cid(HashFunction::Sha2_256),
+            Err(Error::TypeMismatch { path, .. }) if path == "[0][0]"
+        ));
+    }
+
+    #[test]
+    fn can_address_tagged_values() {
+        let node = Node::from_serializable(&(Timestamp::new(86_400, 0), "a")).unwrap();
+
+        assert_eq!(
+            node.block(HashFunction::Sha2_256).unwrap(),
+            [0x82, 0x82, 0x01, 0x1a, 0x00, 0x01, 0x51, 0x80, 0x61, 0x61]
+        );
+        assert_eq!(
+            Node::from_serializable(&Timestamp::new(86_400, 0))
+                .unwrap()
+                .cid(HashFunction::Sha2_256)
+                .unwrap(),
+            Node::from_serializable(&(1u8, 86_400u32))
+                .unwrap()
+                .cid(HashFunction::Sha2_256)
+                .unwrap()
+        );
+        assert_ne!(
+            node.cid(HashFunction::Sha2_256).unwrap(),
+            Node::from_serializable(&(Timestamp::new(86_400, 1), "a"))
+                .unwrap()
+                .cid(HashFunction::Sha2_256)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn can_address_links() {
+        let linked = Node::from_serializable(&(vec![1u8],)).unwrap();
+        let mut cid = vec![0];
+        cid.extend(
+            linked.children[0]
+                .cid(HashFunction::Sha2_256)
+                .unwrap()
+                .to_bytes(),
+        );
+
+        let link = Node::new(CborValue::Array(vec![CborValue::Tag(
+            LINK_TAG,
+            Box::new(CborValue::Bytes(cid)),
+        )]))
+        .unwrap();
+
+        assert_eq!(
+            link.cid(HashFunction::Sha2_256).unwrap(),
+            linked.cid(HashFunction::Sha2_256).unwrap()
+        );
+        let not_a_link = Node::new(CborValue::Array(vec![CborValue::Tag(
+            LINK_TAG,
+            Box::new(CborValue::Text(String::from("a"))),
+        )]))
+        .unwrap();
+        assert!(matches!(
+            not_a_link.cid(HashFunction::Sha2_256),
+            Err(Error::TypeMismatch { path, .. }) if path == "[0]"
+        ));
+    }
+}
@@ -0,0 +1,597 @@
+//! JSON interoperability of data trees, going through the same key trees as Cbor values.
+//!
+//! JSON has a narrower data model than Cbor, so values are converted following these rules:
+//! - numbers written without a fraction nor an exponent are integers, any other number is a
+//!   float. Integers are parsed from their literal, so that they can fit the whole Cbor integer
+//!   range, `-2^64` to `2^64 - 1`, without changing how `serde_json` handles numbers elsewhere.
+//!   Floats are written back with a fraction or an exponent, so that `1.0` stays a float, and
+//!   numbers overflowing 64-bit floats are rejected ;
+//! - NaN and infinities have no JSON representation, data trees holding them cannot be converted
+//!   to JSON ;
+//! - byte strings follow the DAG-JSON convention, as an object whose sole `"/"` key maps to an
+//!   object whose sole `"bytes"` key holds the bytes in unpadded standard base64. Padded base64
+//!   is accepted as well, as long as the encoding is canonical. Objects of this exact form are
+//!   always read as byte strings ;
+//! - Cbor tags have no JSON representation, tagged values are written without their tag ;
+//! - maps whose keys are all text are written as objects, other maps as arrays of `[key, value]`
+//!   pairs. Both forms are accepted for maps when reading JSON, objects holding a key twice are
+//!   rejected ;
+//! - structures, tuples and enumerations are written as by
+//!   [`assign_keys`](super::Node::assign_keys), and read as by
+//!   [`strip_keys`](super::Node::strip_keys).
+//!
+//! With these rules, converting a data tree to JSON and back gives the same data tree, as long as
+//! it holds no non-finite float nor tagged value.
+
+use super::Node;
+use crate::internal::error::{index_path, key_path, Error, ROOT_PATH};
+use crate::internal::key_tree::Node as KeyNode;
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_cbor::Value as CborValue;
+use serde_json::ser::{Formatter, PrettyFormatter};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// Key of the DAG-JSON object wrapping special values
+const SPECIAL_KEY: &str = "/";
+/// Key under which the DAG-JSON object of a byte string holds its base64 encoding
+const BYTES_KEY: &str = "bytes";
+
+/// Smallest integer of the Cbor integer range
+const CBOR_INTEGER_MIN: i128 = -(1 << 64);
+/// Largest integer of the Cbor integer range
+const CBOR_INTEGER_MAX: i128 = (1 << 64) - 1;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+impl Node {
+    /// Create a data tree from a JSON text holding keys, following the given key tree
+    pub fn from_json(json: &str, key_node: &KeyNode) -> Result<Self, Error> {
+        Node::strip_keys(json_to_cbor(json, ROOT_PATH)?, key_node)
+    }
+
+    /// Fuse a key tree and the data tree to generate a compact JSON text based on them
+    pub fn to_json_with_keys(&self, key_node: &KeyNode) -> Result<String, Error> {
+        self.write_json(key_node, serde_json::ser::CompactFormatter)
+    }
+
+    /// Fuse a key tree and the data tree to generate an indented JSON text based on them
+    pub fn to_json_pretty_with_keys(&self, key_node: &KeyNode) -> Result<String, Error> {
+        self.write_json(key_node, PrettyFormatter::new())
+    }
+
+    fn write_json<F: Formatter>(&self, key_node: &KeyNode, formatter: F) -> Result<String, Error> {
+        let json = cbor_to_json(self.assign_keys(key_node)?, ROOT_PATH)?;
+        let mut bytes = Vec::new();
+        json.serialize(&mut serde_json::Serializer::with_formatter(
+            &mut bytes, formatter,
+        ))
+        .map_err(|e| type_mismatch(ROOT_PATH, e))?;
+        String::from_utf8(bytes).map_err(|e| type_mismatch(ROOT_PATH, e))
+    }
+}
+
+fn type_mismatch(path: &str, message: impl ToString) -> Error {
+    Error::TypeMismatch {
+        path: String::from(path),
+        message: message.to_string(),
+    }
+}
+
+/// Convert a JSON text to a Cbor value
+fn json_to_cbor(json: &str, path: &str) -> Result<CborValue, Error> {
+    let mut reader = JsonReader { json, position: 0 };
+    let value = reader.value(path)?;
+    match reader.peek() {
+        None => Ok(value),
+        Some(_) => Err(reader.unexpected(path)),
+    }
+}
+
+/// Reader converting a JSON text to a Cbor value in a single pass. Numbers are parsed from their
+/// literal instead of going through `serde_json` numbers, which do not hold integers beyond 64
+/// bits, while strings are unescaped by `serde_json`.
+struct JsonReader<'a> {
+    json: &'a str,
+    position: usize,
+}
+
+impl<'a> JsonReader<'a> {
+    /// Next byte that is not whitespace, if any
+    fn peek(&mut self) -> Option<u8> {
+        let bytes = self.json.as_bytes();
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = bytes.get(self.position) {
+            self.position += 1;
+        }
+        bytes.get(self.position).copied()
+    }
+
+    fn unexpected(&self, path: &str) -> Error {
+        match self.json[self.position..].chars().next() {
+            Some(c) => type_mismatch(
+                path,
+                format!("unexpected character `{}` at byte {}", c, self.position),
+            ),
+            None => type_mismatch(path, "unexpected end of JSON text"),
+        }
+    }
+
+    /// Consume the given byte, which has to be the next one that is not whitespace
+    fn expect(&mut self, byte: u8, path: &str) -> Result<(), Error> {
+        match self.peek() {
+            Some(b) if b == byte => {
+                self.position += 1;
+                Ok(())
+            }
+            _ => Err(self.unexpected(path)),
+        }
+    }
+
+    /// Consume the literal if the text continues with it
+    fn consume(&mut self, literal: &str) -> bool {
+        let found = self.json[self.position..].starts_with(literal);
+        if found {
+            self.position += literal.len();
+        }
+        found
+    }
+
+    fn value(&mut self, path: &str) -> Result<CborValue, Error> {
+        Ok(match self.peek() {
+            Some(b'{') => self.object(path)?,
+            Some(b'[') => CborValue::Array(self.array(path)?),
+            Some(b'"') => CborValue::Text(self.string(path)?),
+            Some(b'-' | b'0'..=b'9') => self.number(path)?,
+            _ if self.consume("null") => CborValue::Null,
+            _ if self.consume("true") => CborValue::Bool(true),
+            _ if self.consume("false") => CborValue::Bool(false),
+            _ => return Err(self.unexpected(path)),
+        })
+    }
+
+    fn array(&mut self, path: &str) -> Result<Vec<CborValue>, Error> {
+        self.expect(b'[', path)?;
+        let mut values = Vec::new();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(values);
+        }
+        loop {
+            values.push(self.value(&index_path(path, values.len()))?);
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(values);
+                }
+                _ => return Err(self.unexpected(path)),
+            }
+        }
+    }
+
+    fn object(&mut self, path: &str) -> Result<CborValue, Error> {
+        self.expect(b'{', path)?;
+        let mut map = BTreeMap::new();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(CborValue::Map(map));
+        }
+        loop {
+            let key = self.string(path)?;
+            self.expect(b':', path)?;
+            let key_path = key_path(path, &key);
+            let value = self.value(&key_path)?;
+            if map.insert(CborValue::Text(key), value).is_some() {
+                return Err(type_mismatch(&key_path, "duplicate key"));
+            }
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    break;
+                }
+                _ => return Err(self.unexpected(path)),
+            }
+        }
+
+        match bytes_object(&map) {
+            Some(encoded) => base64_decode(encoded)
+                .map(CborValue::Bytes)
+                .ok_or_else(|| type_mismatch(path, "invalid base64 byte string")),
+            None => Ok(CborValue::Map(map)),
+        }
+    }
+
+    fn string(&mut self, path: &str) -> Result<String, Error> {
+        if self.peek() != Some(b'"') {
+            return Err(self.unexpected(path));
+        }
+        let start = self.position;
+        let bytes = self.json.as_bytes();
+        let mut escaped = false;
+        for (i, &b) in bytes.iter().enumerate().skip(start + 1) {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => {
+                    self.position = i + 1;
+                    return serde_json::from_str(&self.json[start..self.position])
+                        .map_err(|e| type_mismatch(path, e));
+                }
+                _ => {}
+            }
+        }
+        self.position = bytes.len();
+        Err(self.unexpected(path))
+    }
+
+    fn number(&mut self, path: &str) -> Result<CborValue, Error> {
+        let start = self.position;
+        let length = self.json[start..]
+            .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+            .unwrap_or(self.json.len() - start);
+        self.position += length;
+        number_to_cbor(&self.json[start..self.position], path)
+    }
+}
+
+fn number_to_cbor(literal: &str, path: &str) -> Result<CborValue, Error> {
+    // Literal is checked to be a JSON number before being parsed by hand
+    serde_json::from_str::<serde_json::Number>(literal).map_err(|e| type_mismatch(path, e))?;
+    if literal.contains(&['.', 'e', 'E'][..]) {
+        return match literal.parse::<f64>() {
+            Ok(v) if v.is_finite() => Ok(CborValue::Float(v)),
+            _ => Err(type_mismatch(
+                path,
+                format!("number {} is out of the range of 64-bit floats", literal),
+            )),
+        };
+    }
+
+    match literal.parse::<i128>() {
+        Ok(v) if (CBOR_INTEGER_MIN..=CBOR_INTEGER_MAX).contains(&v) => Ok(CborValue::Integer(v)),
+        _ => Err(type_mismatch(
+            path,
+            format!("integer {} is out of the Cbor integer range", literal),
+        )),
+    }
+}
+
+/// Base64 encoding of a byte string, if the object follows the DAG-JSON bytes form
+fn bytes_object(map: &BTreeMap<CborValue, CborValue>) -> Option<&str> {
+    match map.get(&CborValue::Text(String::from(SPECIAL_KEY))) {
+        Some(CborValue::Map(special)) if map.len() == 1 && special.len() == 1 => {
+            match special.get(&CborValue::Text(String::from(BYTES_KEY))) {
+                Some(CborValue::Text(encoded)) => Some(encoded),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// JSON value checked to be representable, holding integers of the whole Cbor integer range
+enum Json {
+    Null,
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Serialize for Json {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Json::Null => serializer.serialize_unit(),
+            Json::Bool(v) => serializer.serialize_bool(*v),
+            Json::Integer(v) => serializer.serialize_i128(*v),
+            Json::Float(v) => serializer.serialize_f64(*v),
+            Json::String(v) => serializer.serialize_str(v),
+            Json::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Json::Object(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+fn cbor_to_json(cbor: CborValue, path: &str) -> Result<Json, Error> {
+    Ok(match cbor {
+        CborValue::Null => Json::Null,
+        CborValue::Bool(v) => Json::Bool(v),
+        CborValue::Integer(v) => Json::Integer(v),
+        CborValue::Float(v) if v.is_finite() => Json::Float(v),
+        CborValue::Float(v) => {
+            return Err(type_mismatch(
+                path,
+                format!("{} cannot be represented in JSON", v),
+            ))
+        }
+        CborValue::Bytes(v) => Json::Object(vec![(
+            String::from(SPECIAL_KEY),
+            Json::Object(vec![(
+                String::from(BYTES_KEY),
+                Json::String(base64_encode(&v)),
+            )]),
+        )]),
+        CborValue::Text(v) => Json::String(v),
+        CborValue::Tag(_, value) => cbor_to_json(*value, path)?,
+        CborValue::Array(values) => Json::Array(
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| cbor_to_json(value, &index_path(path, i)))
+                .collect::<Result<_, _>>()?,
+        ),
+        CborValue::Map(map) => map_to_json(map, path)?,
+        CborValue::__Hidden => unreachable!(),
+    })
+}
+
+fn map_to_json(map: BTreeMap<CborValue, CborValue>, path: &str) -> Result<Json, Error> {
+    if map.keys().all(|key| matches!(key, CborValue::Text(_))) {
+        let mut object = Vec::new();
+        for (key, value) in map {
+            if let CborValue::Text(key) = key {
+                let value = cbor_to_json(value, &key_path(path, &key))?;
+                object.push((key, value));
+            }
+        }
+        // Cbor maps order keys by length first, objects are written in lexicographic order
+        object.sort_by(|(a, _), (b, _)| a.cmp(b));
+        return Ok(Json::Object(object));
+    }
+
+    map.into_iter()
+        .enumerate()
+        .map(|(i, (key, value))| {
+            let entry_path = index_path(path, i);
+            Ok(Json::Array(vec![
+                cbor_to_json(key, &entry_path)?,
+                cbor_to_json(value, &entry_path)?,
+            ]))
+        })
+        .collect::<Result<_, _>>()
+        .map(Json::Array)
+}
+
+/// Unpadded standard base64 encoding
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (i, &b)| group | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(char::from(
+                BASE64_ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize],
+            ));
+        }
+    }
+    encoded
+}
+
+/// Standard base64 decoding, padded or not. Only canonical encodings are accepted: padding has to
+/// complete the last group, and bits left over by the last byte have to be zero.
+fn base64_decode(padded: &str) -> Option<Vec<u8>> {
+    let encoded = padded.trim_end_matches('=');
+    let padding = padded.len() - encoded.len();
+    if encoded.len() % 4 == 1 || (padding > 0 && padding != (4 - encoded.len() % 4) % 4) {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.as_bytes().chunks(4) {
+        let mut group = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let sextet = BASE64_ALPHABET.iter().position(|a| a == c)?;
+            group |= u32::try_from(sextet).ok()? << (18 - 6 * i);
+        }
+        if group & (0xff_ffff >> (8 * (chunk.len() - 1))) != 0 {
+            return None;
+        }
+        bytes.extend_from_slice(&group.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::key_tree::{GenerateNode, StaticNode};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        count: u64,
+        offset: i128,
+        ratio: f64,
+        #[serde(with = "serde_bytes_vec")]
+        data: Vec<u8>,
+        labels: HashMap<u8, String>,
+    }
+
+    impl GenerateNode for Sample {
+        const KEY_NODE: StaticNode = StaticNode {
+            children: &[
+                StaticNode {
+                    value: Some("count"),
+                    ..u64::KEY_NODE
+                },
+                StaticNode {
+                    value: Some("offset"),
+                    ..i128::KEY_NODE
+                },
+                StaticNode {
+                    value: Some("ratio"),
+                    ..f64::KEY_NODE
+                },
+                StaticNode {
+                    value: Some("data"),
+                    ..StaticNode::DEFAULT
+                },
+                StaticNode {
+                    value: Some("labels"),
+                    ..HashMap::<u8, String>::KEY_NODE
+                },
+            ],
+            ..StaticNode::DEFAULT
+        };
+    }
+
+    /// Serialize byte vectors as Cbor byte strings rather than sequences
+    mod serde_bytes_vec {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(bytes)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<u8>, D::Error> {
+            serde_cbor::Value::deserialize(deserializer).and_then(|value| match value {
+                serde_cbor::Value::Bytes(bytes) => Ok(bytes),
+                _ => Err(serde::de::Error::custom("expected a byte string")),
+            })
+        }
+    }
+
+    #[test]
+    fn can_round_trip_json() {
+        let mut labels = HashMap::new();
+        labels.insert(7, String::from("seven"));
+        let sample = Sample {
+            count: u64::MAX,
+            offset: -(1 << 64),
+            ratio: 1.0,
+            data: vec![0xde, 0xad, 0xbe, 0xef],
+            labels,
+        };
+        let data_tree = Node::from_serializable(&sample).unwrap();
+
+        let json = data_tree
+            .to_json_with_keys(&Sample::generate_node())
+            .unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"count":18446744073709551615,"data":{"/":{"bytes":"3q2+7w"}},"labels":[[7,"seven"]],"offset":-18446744073709551616,"ratio":1.0}"#
+        );
+        let read = Node::from_json(&json, &Sample::generate_node()).unwrap();
+        assert_eq!(read, data_tree);
+        assert_eq!(read.deserialize::<Sample>().unwrap(), sample);
+    }
+
+    #[test]
+    fn can_read_positional_json() {
+        let json = r#"[3, 4, 0.5, {"/": {"bytes": "AAE="}}, {}]"#;
+
+        let data_tree = Node::from_json(json, &Sample::generate_node()).unwrap();
+
+        assert_eq!(
+            data_tree.deserialize::<Sample>().unwrap(),
+            Sample {
+                count: 3,
+                offset: 4,
+                ratio: 0.5,
+                data: vec![0, 1],
+                labels: HashMap::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn cannot_convert_out_of_range_values() {
+        assert_eq!(
+            Node::from_json("[1, 18446744073709551616]", &Vec::<u64>::generate_node()).unwrap_err(),
+            Error::TypeMismatch {
+                path: String::from("[1]"),
+                message: String::from(
+                    "integer 18446744073709551616 is out of the Cbor integer range"
+                ),
+            }
+        );
+
+        let data_tree = Node::from_serializable(&(f64::NAN,)).unwrap();
+        assert!(matches!(
+            data_tree.to_json_with_keys(&<(f64,)>::generate_node()),
+            Err(Error::TypeMismatch { path, .. }) if path == "[0]"
+        ));
+    }
+
+    #[test]
+    fn cannot_read_invalid_json() {
+        let mismatch = |json: &str, key_node: KeyNode| match Node::from_json(json, &key_node) {
+            Err(Error::TypeMismatch { path, .. }) => path,
+            result => panic!("unexpected result for {}: {:?}", json, result),
+        };
+
+        assert_eq!(mismatch("[0.5, 1e400]", Vec::<f64>::generate_node()), "[1]");
+        assert_eq!(mismatch("[-1e400]", Vec::<f64>::generate_node()), "[0]");
+        assert_eq!(
+            mismatch(
+                r#"{"low": 1, "low": 2}"#,
+                HashMap::<String, u32>::generate_node()
+            ),
+            "low"
+        );
+        for encoded in ["Zg=", "Zg===", "Zm9v=", "Zh", "Zm9=", "Zm8=="] {
+            let json = format!(r#"[{{"/": {{"bytes": "{}"}}}}]"#, encoded);
+            assert_eq!(mismatch(&json, Vec::<Vec<u8>>::generate_node()), "[0]");
+        }
+        assert_eq!(mismatch("[1] 2", Vec::<u32>::generate_node()), ".");
+        assert_eq!(mismatch(r#"[1, "two"#, Vec::<u32>::generate_node()), "[1]");
+    }
+
+    #[test]
+    fn leaves_serde_json_numbers_untouched() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Measure {
+            unit: String,
+            #[serde(flatten)]
+            values: HashMap<String, f64>,
+        }
+
+        let measure: Measure = serde_json::from_str(r#"{"unit": "m", "low": 1.5}"#).unwrap();
+
+        assert_eq!(measure.values["low"], 1.5);
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        for (bytes, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg"),
+        ] {
+            assert_eq!(base64_encode(bytes), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), bytes);
+        }
+        assert_eq!(base64_decode("Zm8=").unwrap(), b"fo");
+        assert_eq!(base64_decode("Zg==").unwrap(), b"f");
+        assert_eq!(base64_decode("Zm9vY"), None);
+        assert_eq!(base64_decode("Zm9v!"), None);
+        assert_eq!(base64_decode("Zg="), None);
+        assert_eq!(base64_decode("Zm9v=="), None);
+        assert_eq!(base64_decode("Zh"), None);
+        assert_eq!(base64_decode("Zm9="), None);
+    }
+}
@@ -0,0 +1,381 @@
+//! Serializer building data trees straight from Rust values.
+//!
+//! Contrary to going through a Cbor value, the children of a structure are kept in the order its
+//! fields are serialized, which is the order of the key tree generated for it. It also allows to
+//! tell structures, whose keys are dropped, from maps, whose keys are held as data.
+
+use super::{canonical_float, newtype_tag, Node, Value};
+use serde::ser::{self, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Debug)]
+pub(crate) struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+fn leaf(value: Value) -> Result<Node, Error> {
+    Ok(Node {
+        value: Some(value),
+        children: vec![],
+        tag: None,
+    })
+}
+
+fn non_leaf(children: Vec<Node>) -> Node {
+    Node {
+        value: None,
+        children,
+        tag: None,
+    }
+}
+
+/// Node of an enum variant carrying data, holding the variant name followed by its content
+fn variant(name: &str, content: Node) -> Node {
+    let name = Node {
+        value: Some(Value::Text(name.to_string())),
+        children: vec![],
+        tag: None,
+    };
+    non_leaf(vec![name, content])
+}
+
+pub(crate) struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Node;
+    type Error = Error;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVec;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeVec;
+    type SerializeStructVariant = SerializeVec;
+
+    fn serialize_bool(self, v: bool) -> Result<Node, Error> {
+        leaf(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Node, Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Node, Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Node, Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Node, Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Node, Error> {
+        leaf(Value::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Node, Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Node, Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Node, Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Node, Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Node, Error> {
+        match i128::try_from(v) {
+            Ok(v) => self.serialize_i128(v),
+            Err(_) => Err(Error(String::from("integer is out of range"))),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Node, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Node, Error> {
+        leaf(Value::Float(canonical_float(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Node, Error> {
+        leaf(Value::Text(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Node, Error> {
+        leaf(Value::Text(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Node, Error> {
+        leaf(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Node, Error> {
+        leaf(Value::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Node, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Node, Error> {
+        leaf(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant_name: &'static str,
+    ) -> Result<Node, Error> {
+        // Unit variants are serialized as their name, like Cbor does. It is also the way serde
+        // serializes the tag of adjacently tagged enums.
+        leaf(Value::Text(variant_name.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Node, Error> {
+        let node = value.serialize(self)?;
+        Ok(match newtype_tag(name) {
+            Some(tag) => node.tagged(tag),
+            None => node,
+        })
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant_name: &'static str,
+        value: &T,
+    ) -> Result<Node, Error> {
+        Ok(variant(variant_name, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec, Error> {
+        Ok(SerializeVec {
+            variant: None,
+            children: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant_name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, Error> {
+        Ok(SerializeVec {
+            variant: Some(variant_name),
+            children: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeVec, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant_name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, Error> {
+        Ok(SerializeVec {
+            variant: Some(variant_name),
+            children: Vec::with_capacity(len),
+        })
+    }
+}
+
+/// Serializes sequences, tuples and structures, whose elements or fields become ordered children
+pub(crate) struct SerializeVec {
+    variant: Option<&'static str>,
+    children: Vec<Node>,
+}
+
+impl SerializeVec {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.children.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn done(self) -> Result<Node, Error> {
+        let node = non_leaf(self.children);
+        Ok(match self.variant {
+            Some(variant_name) => variant(variant_name, node),
+            None => node,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        self.done()
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        self.done()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        self.done()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        self.done()
+    }
+}
+
+impl ser::SerializeStruct for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        self.done()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVec {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        self.done()
+    }
+}
+
+/// Serializes maps, whose entries become children holding a key node and a value node, sorted by
+/// their key. Serde also serializes structures with flattened fields as maps, keyed by the names of
+/// their fields, which are kept as entries as well.
+pub(crate) struct SerializeMap {
+    entries: Vec<Node>,
+    next_key: Option<Node>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.next_key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| Error(String::from("map value serialized before its key")))?;
+        let value = value.serialize(Serializer)?;
+        self.entries.push(non_leaf(vec![key, value]));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, Error> {
+        super::sort_entries(self.entries)
+            .map(non_leaf)
+            .map_err(|e| Error(e.to_string()))
+    }
+}
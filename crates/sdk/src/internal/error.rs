@@ -0,0 +1,126 @@
+//! Errors that can arise in generated code while a transformation is being invoked by the host.
+//! They are CBOR-encoded and handed back to the host through the error channel described in
+//! [`memory`](crate::internal::memory).
+
+use crate::internal::data_tree::Node;
+use serde::{Deserialize, Serialize};
+
+/// Path pointing to the root of a payload, following the `a.b[0].c` notation used in field paths
+pub(crate) const ROOT_PATH: &str = ".";
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Error reported to the host when an exported transformation could not be run
+pub enum Error {
+    /// Input payload could not be decoded as a CBOR data tree
+    #[error("failed to decode input payload: {0}")]
+    PayloadDecode(String),
+    /// Structure of the input data tree does not match the key tree of the transformation inputs
+    #[error("input payload does not match expected key tree at `{path}`")]
+    KeyTreeMismatch { path: String },
+    /// A value in the input payload could not be converted to the expected Rust type
+    #[error("type mismatch at `{path}`: {message}")]
+    TypeMismatch { path: String, message: String },
+    /// Output of the transformation could not be encoded as a CBOR data tree
+    #[error("failed to encode output: {0}")]
+    OutputEncode(String),
+    /// Transformation panicked while running
+    #[error("transformation `{transformation}` panicked at {location}: {message}")]
+    Panic {
+        transformation: String,
+        message: String,
+        location: String,
+    },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// Failure returned by a transformation through the `Err` variant of its `Result` output
+pub struct Failure {
+    /// `Display` text of the returned error
+    pub message: String,
+    /// Returned error as a data tree, only set if the error type is serializable
+    pub payload: Option<Node>,
+}
+
+/// Wrapper around the error returned by a transformation, used by generated code to fetch its
+/// payload whether its type implements `Serialize` or not.
+///
+/// Method resolution picks [`SerializableFailure`] when possible and falls back on
+/// [`OpaqueFailure`] otherwise, provided that `payload` is called on `&FailurePayload(&e)` with
+/// both traits in scope.
+#[doc(hidden)]
+pub struct FailurePayload<'a, E>(pub &'a E);
+
+#[doc(hidden)]
+pub trait SerializableFailure {
+    fn payload(&self) -> Option<Node>;
+}
+
+impl<E: Serialize> SerializableFailure for FailurePayload<'_, E> {
+    fn payload(&self) -> Option<Node> {
+        Node::from_serializable(self.0).ok()
+    }
+}
+
+#[doc(hidden)]
+pub trait OpaqueFailure {
+    fn payload(&self) -> Option<Node> {
+        None
+    }
+}
+
+impl<E> OpaqueFailure for &FailurePayload<'_, E> {}
+
+/// Build the path of a keyed child from the path of its parent
+pub(crate) fn key_path(parent: &str, key: &str) -> String {
+    if parent == ROOT_PATH {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Build the path of an indexed child from the path of its parent
+pub(crate) fn index_path(parent: &str, index: usize) -> String {
+    if parent == ROOT_PATH {
+        format!("[{}]", index)
+    } else {
+        format!("{}[{}]", parent, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_build_paths() {
+        assert_eq!(key_path(ROOT_PATH, "arg0"), "arg0");
+        assert_eq!(index_path(ROOT_PATH, 1), "[1]");
+        assert_eq!(key_path(&index_path("arg0", 2), "number"), "arg0[2].number");
+    }
+
+    #[test]
+    fn can_encode_error_body() {
+        let error = Error::KeyTreeMismatch {
+            path: String::from("arg0"),
+        };
+        let error_cbor = serde_cbor::to_vec(&error).unwrap();
+        assert_eq!(serde_cbor::from_slice::<Error>(&error_cbor).unwrap(), error);
+    }
+
+    #[test]
+    #[allow(clippy::needless_borrow)]
+    fn can_fetch_failure_payload() {
+        #[derive(Serialize)]
+        struct SerializableError {
+            code: u8,
+        }
+        struct OpaqueError;
+
+        assert_eq!(
+            (&FailurePayload(&SerializableError { code: 1 })).payload(),
+            Some(Node::new(serde_cbor::Value::from(vec![serde_cbor::Value::Integer(1)])).unwrap())
+        );
+        assert_eq!((&FailurePayload(&OpaqueError)).payload(), None);
+    }
+}
@@ -1,23 +1,149 @@
 //! Key trees are responsible for recursively holding structure keys used as I/O paramters in transformations
+//!
+//! Key trees are built at compile time, as the [`GenerateNode::KEY_NODE`] constant of each type,
+//! so that they can be embedded in the manifest of a module. Key trees of recursive types would be
+//! infinite. Implementations generated for such types thus implement [`Recursive`] and replace
+//! the occurrences of the type in its own fields by [`Recursion`], whose key node is a
+//! [`Shape::Recursive`] node referencing the type. The key tree of such node is only generated
+//! when data is actually found under it.
+//!
+//! Only direct recursion is cut this way: types nested in each other, or in themselves with other
+//! type parameters, cannot have a key tree and fail to compile.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, Wrapping,
+};
+use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Default, Debug, Eq, PartialEq)]
 pub struct Node {
     pub value: Option<&'static str>,
     pub children: Vec<Node>,
+    pub shape: Shape,
+}
+
+/// Key node built at compile time, from which [`Node`]s are generated
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct StaticNode {
+    pub value: Option<&'static str>,
+    pub children: &'static [StaticNode],
+    pub shape: Shape,
+    /// Name of the recursive type described by the node, referenced by the [`Shape::Recursive`]
+    /// nodes under it
+    pub type_name: Option<&'static str>,
+}
+
+impl StaticNode {
+    /// Key node of a scalar value
+    pub const DEFAULT: StaticNode = StaticNode {
+        value: None,
+        children: &[],
+        shape: Shape::Plain,
+        type_name: None,
+    };
+}
+
+impl From<&StaticNode> for Node {
+    fn from(node: &StaticNode) -> Self {
+        Node {
+            value: node.value,
+            children: node.children.iter().map(Node::from).collect(),
+            shape: node.shape,
+        }
+    }
+}
+
+/// Shape of the data described by a key node, telling how its children should be read
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Shape {
+    /// Scalar value, or structure whose children hold the keys of its fields
+    #[default]
+    Plain,
+    /// Homogeneous sequence whose single child holds the key node of every element
+    Sequence,
+    /// Fixed-size array of the given length, whose single child holds the key node of every
+    /// element
+    Array(usize),
+    /// Enumeration whose children hold the keys of its variants. Matching data nodes hold the name
+    /// of the variant as a first child, followed by the variant content if any.
+    Enum,
+    /// Map with dynamic keys, whose two children hold the key nodes of its keys and of its values.
+    /// Matching data nodes hold one child per entry, each entry holding a key and a value child.
+    Map,
+    /// Structure with flattened fields, whose children hold the keys of its fields. Flattened
+    /// fields are unnamed children, holding the key node of the structure or map whose entries
+    /// they add. Matching data nodes hold one entry per field, as maps do, since serde serializes
+    /// such structures as maps keyed by field names.
+    Flattened,
+    /// Tuple whose children, named after their position, hold the key nodes of its elements
+    Tuple,
+    /// Type nested in itself, whose key node is generated on demand from the given reference
+    Recursive(Reference),
+}
+
+/// Reference to a type whose key node has not been generated to stop an infinite recursion
+#[derive(Clone, Copy)]
+pub struct Reference {
+    /// Name of the referenced type, labeling the closest node above the reference that describes
+    /// it
+    pub type_name: &'static str,
+    pub generate: &'static (dyn Fn() -> Node + Sync),
+}
+
+impl fmt::Debug for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Reference").field(&self.type_name).finish()
+    }
 }
 
+impl PartialEq for Reference {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_name == other.type_name
+    }
+}
+
+impl Eq for Reference {}
+
 /// Trait meant to generate key node for supported native rust types
 pub trait GenerateNode {
-    fn generate_node() -> Node;
+    /// Key node of the type, built at compile time
+    const KEY_NODE: StaticNode;
+
+    fn generate_node() -> Node {
+        Node::from(&Self::KEY_NODE)
+    }
+}
+
+/// Type nested in itself, whose key node is labeled with its name
+pub trait Recursive: GenerateNode {
+    const TYPE_NAME: &'static str;
+}
+
+/// Stand-in for a recursive type in its own fields, whose key node references the type instead of
+/// holding its key node
+pub struct Recursion<T: ?Sized>(PhantomData<T>);
+
+impl<T: Recursive + ?Sized + 'static> GenerateNode for Recursion<T> {
+    const KEY_NODE: StaticNode = StaticNode {
+        shape: Shape::Recursive(Reference {
+            type_name: T::TYPE_NAME,
+            generate: &T::generate_node,
+        }),
+        ..StaticNode::DEFAULT
+    };
 }
 
 macro_rules! primitives_impl {
     ($ty:ident) => {
         impl GenerateNode for $ty {
-            #[inline]
-            fn generate_node() -> Node {
-                Node::default()
-            }
+            const KEY_NODE: StaticNode = StaticNode::DEFAULT;
         }
     };
 }
@@ -40,51 +166,150 @@ primitives_impl!(f64);
 primitives_impl!(char);
 primitives_impl!(str);
 primitives_impl!(String);
+primitives_impl!(NonZeroIsize);
+primitives_impl!(NonZeroI8);
+primitives_impl!(NonZeroI16);
+primitives_impl!(NonZeroI32);
+primitives_impl!(NonZeroI64);
+primitives_impl!(NonZeroI128);
+primitives_impl!(NonZeroUsize);
+primitives_impl!(NonZeroU8);
+primitives_impl!(NonZeroU16);
+primitives_impl!(NonZeroU32);
+primitives_impl!(NonZeroU64);
+primitives_impl!(NonZeroU128);
 
-impl<T> GenerateNode for [T] {
-    #[inline]
-    fn generate_node() -> Node {
-        Node::default()
-    }
+impl<T: GenerateNode> GenerateNode for [T] {
+    const KEY_NODE: StaticNode = Vec::<T>::KEY_NODE;
 }
 
-impl<T> GenerateNode for Vec<T> {
-    #[inline]
-    fn generate_node() -> Node {
-        Node::default()
-    }
+impl<T: GenerateNode> GenerateNode for Vec<T> {
+    /// Key node of a homogeneous sequence
+    const KEY_NODE: StaticNode = StaticNode {
+        children: &[T::KEY_NODE],
+        shape: Shape::Sequence,
+        ..StaticNode::DEFAULT
+    };
+}
+
+impl<T: GenerateNode, const N: usize> GenerateNode for [T; N] {
+    const KEY_NODE: StaticNode = StaticNode {
+        children: &[T::KEY_NODE],
+        shape: Shape::Array(N),
+        ..StaticNode::DEFAULT
+    };
+}
+
+impl<K, V, S> GenerateNode for HashMap<K, V, S>
+where
+    K: GenerateNode,
+    V: GenerateNode,
+{
+    const KEY_NODE: StaticNode = BTreeMap::<K, V>::KEY_NODE;
+}
+
+impl<K, V> GenerateNode for BTreeMap<K, V>
+where
+    K: GenerateNode,
+    V: GenerateNode,
+{
+    /// Key node of a map whose keys are held as data
+    const KEY_NODE: StaticNode = StaticNode {
+        children: &[K::KEY_NODE, V::KEY_NODE],
+        shape: Shape::Map,
+        ..StaticNode::DEFAULT
+    };
+}
+
+#[cfg(feature = "indexmap")]
+impl<K, V, S> GenerateNode for indexmap::IndexMap<K, V, S>
+where
+    K: GenerateNode,
+    V: GenerateNode,
+{
+    const KEY_NODE: StaticNode = BTreeMap::<K, V>::KEY_NODE;
 }
 
-macro_rules! array_impls {
-    ($($len:tt)+) => {
+impl GenerateNode for () {
+    const KEY_NODE: StaticNode = StaticNode::DEFAULT;
+}
+
+macro_rules! tuple_impls {
+    ($($len:tt => ($($n:tt $name:ident)+))+) => {
         $(
-            impl<T> GenerateNode for [T; $len] {
-                #[inline]
-                fn generate_node() -> Node {
-                    Node::default()
-                }
+            impl<$($name: GenerateNode),+> GenerateNode for ($($name,)+) {
+                const KEY_NODE: StaticNode = StaticNode {
+                    children: &[
+                        $(
+                            StaticNode {
+                                value: Some(stringify!($n)),
+                                ..$name::KEY_NODE
+                            }
+                        ),+
+                    ],
+                    shape: Shape::Tuple,
+                    ..StaticNode::DEFAULT
+                };
             }
         )+
     }
 }
 
-array_impls! {
-    0  01 02 03 04 05 06 07 08 09
-    10 11 12 13 14 15 16 17 18 19
-    20 21 22 23 24 25 26 27 28 29
-    30 31 32
+tuple_impls! {
+    1  => (0 T0)
+    2  => (0 T0 1 T1)
+    3  => (0 T0 1 T1 2 T2)
+    4  => (0 T0 1 T1 2 T2 3 T3)
+    5  => (0 T0 1 T1 2 T2 3 T3 4 T4)
+    6  => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5)
+    7  => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6)
+    8  => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7)
+    9  => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8)
+    10 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9)
+    11 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10)
+    12 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11)
 }
 
 impl<T> GenerateNode for Option<T>
 where
     T: GenerateNode,
 {
-    #[inline]
-    fn generate_node() -> Node {
-        T::generate_node()
+    const KEY_NODE: StaticNode = T::KEY_NODE;
+}
+
+macro_rules! wrapper_impls {
+    ($($ty:ident <$param:ident $(: ?$bound:ident)?>)+) => {
+        $(
+            impl<$param> GenerateNode for $ty<$param>
+            where
+                $param: GenerateNode $(+ ?$bound)?,
+            {
+                const KEY_NODE: StaticNode = $param::KEY_NODE;
+            }
+        )+
     }
 }
 
+wrapper_impls! {
+    Box<T: ?Sized>
+    Rc<T: ?Sized>
+    Arc<T: ?Sized>
+    RefCell<T: ?Sized>
+    Wrapping<T>
+}
+
+impl<T> GenerateNode for Cow<'static, T>
+where
+    T: GenerateNode + ToOwned + ?Sized,
+{
+    const KEY_NODE: StaticNode = T::KEY_NODE;
+}
+
+impl<T: ?Sized> GenerateNode for PhantomData<T> {
+    // Phantom data is serialized as a unit
+    const KEY_NODE: StaticNode = StaticNode::DEFAULT;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,7 +323,7 @@ mod tests {
                 #[test]
                 fn $name() {
                     $(
-                        assert_eq!(Node { value: None, children: Vec::new() }, $ty::generate_node());
+                        assert_eq!(Node { value: None, children: Vec::new(), shape: Shape::Plain }, $ty::generate_node());
                     )+
                 }
             )+
@@ -109,7 +334,7 @@ mod tests {
                 #[test]
                 fn $name() {
                     $(
-                        assert_eq!(Node { value: None, children: Vec::new() }, $ty::generate_node());
+                        assert_eq!(Node { value: None, children: Vec::new(), shape: Shape::Plain }, $ty::generate_node());
                     )+
                 }
             )+
@@ -136,22 +361,168 @@ mod tests {
             u128,
             str,
             String,
+            NonZeroU8,
+            NonZeroU128,
+            NonZeroI64,
         }
     }
 
     #[test]
     fn test_array() {
-        assert_eq!(Node::default(), <[u8; 32]>::generate_node());
-        assert_eq!(Node::default(), <[u8]>::generate_node());
+        assert_eq!(
+            Node {
+                value: None,
+                children: vec![Node::default()],
+                shape: Shape::Sequence,
+            },
+            <[u8]>::generate_node()
+        );
+        for (len, node) in [
+            (0, <[u8; 0]>::generate_node()),
+            (32, <[u8; 32]>::generate_node()),
+            (1024, <[f64; 1024]>::generate_node()),
+        ] {
+            assert_eq!(
+                Node {
+                    value: None,
+                    children: vec![Node::default()],
+                    shape: Shape::Array(len),
+                },
+                node
+            );
+        }
     }
 
     #[test]
     fn test_vec() {
-        assert_eq!(Node::default(), Vec::<u8>::generate_node());
+        assert_eq!(
+            Node {
+                value: None,
+                children: vec![Node::default()],
+                shape: Shape::Sequence,
+            },
+            Vec::<u8>::generate_node()
+        );
+    }
+
+    #[test]
+    fn test_nested_vec() {
+        assert_eq!(
+            Node {
+                value: None,
+                children: vec![Node {
+                    value: None,
+                    children: vec![Node::default(), Node::default()],
+                    shape: Shape::Map,
+                }],
+                shape: Shape::Sequence,
+            },
+            Vec::<HashMap<u8, ()>>::generate_node()
+        );
+    }
+
+    #[test]
+    fn test_map() {
+        let map_node = Node {
+            value: None,
+            children: vec![Node::default(), Node::default()],
+            shape: Shape::Map,
+        };
+        assert_eq!(map_node, HashMap::<String, u8>::generate_node());
+        assert_eq!(map_node, BTreeMap::<String, u8>::generate_node());
+        #[cfg(feature = "indexmap")]
+        assert_eq!(map_node, indexmap::IndexMap::<String, u8>::generate_node());
+    }
+
+    #[test]
+    fn test_tuple() {
+        assert_eq!(Node::default(), <()>::generate_node());
+        assert_eq!(
+            Node {
+                value: None,
+                children: vec![
+                    Node {
+                        value: Some("0"),
+                        ..Node::default()
+                    },
+                    Node {
+                        value: Some("1"),
+                        children: vec![
+                            Node {
+                                value: Some("0"),
+                                ..Node::default()
+                            },
+                            Node {
+                                value: Some("1"),
+                                ..Node::default()
+                            },
+                        ],
+                        shape: Shape::Tuple,
+                    },
+                ],
+                shape: Shape::Tuple,
+            },
+            <(u8, (String, bool))>::generate_node()
+        );
     }
 
     #[test]
     fn test_option() {
         assert_eq!(Node::default(), Option::<u8>::generate_node());
     }
+
+    #[test]
+    fn test_wrappers() {
+        let tuple_node = <(u8, u8)>::generate_node();
+        assert_eq!(tuple_node, Box::<(u8, u8)>::generate_node());
+        assert_eq!(tuple_node, Rc::<(u8, u8)>::generate_node());
+        assert_eq!(tuple_node, Arc::<(u8, u8)>::generate_node());
+        assert_eq!(tuple_node, RefCell::<(u8, u8)>::generate_node());
+        assert_eq!(tuple_node, Wrapping::<(u8, u8)>::generate_node());
+        assert_eq!(tuple_node, Cow::<'static, (u8, u8)>::generate_node());
+        assert_eq!(Node::default(), Box::<str>::generate_node());
+        assert_eq!(Node::default(), PhantomData::<(u8, u8)>::generate_node());
+    }
+
+    struct Chain;
+
+    impl GenerateNode for Chain {
+        const KEY_NODE: StaticNode = StaticNode {
+            children: &[StaticNode {
+                value: Some("next"),
+                ..Option::<Box<Recursion<Chain>>>::KEY_NODE
+            }],
+            type_name: Some(Chain::TYPE_NAME),
+            ..StaticNode::DEFAULT
+        };
+    }
+
+    impl Recursive for Chain {
+        const TYPE_NAME: &'static str = "Chain";
+    }
+
+    #[test]
+    fn test_recursive() {
+        let chain_node = Chain::generate_node();
+        assert_eq!(
+            Node {
+                value: None,
+                children: vec![Node {
+                    value: Some("next"),
+                    children: vec![],
+                    shape: Shape::Recursive(Reference {
+                        type_name: "Chain",
+                        generate: &Chain::generate_node,
+                    }),
+                }],
+                shape: Shape::Plain,
+            },
+            chain_node
+        );
+        assert_eq!(Chain::KEY_NODE.type_name, Some("Chain"));
+        match chain_node.children[0].shape {
+            Shape::Recursive(reference) => assert_eq!(chain_node, (reference.generate)()),
+            _ => unreachable!(),
+        }
+    }
 }
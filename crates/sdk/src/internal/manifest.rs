@@ -0,0 +1,394 @@
+//! Manifest describing the transformations exported by a module, so that runtimes and tooling can
+//! discover them without running them.
+//!
+//! Every exported transformation contributes an [`Entry`] to the [`SECTION_NAME`] custom section
+//! of the module. Entries are CBOR-encoded at compile time and concatenated by the linker, making
+//! the section a sequence of CBOR items that can be read back with [`parse_section`].
+//!
+//! Each entry holds the key trees of the inputs and outputs of its transformation, taken from the
+//! [`KEY_NODE`](crate::internal::key_tree::GenerateNode::KEY_NODE) constants of their types.
+//! Recursive types are cut where they appear in themselves by [`KeyShape::Recursive`] nodes,
+//! naming the type described by a node above them.
+
+use crate::internal::key_tree::{self, StaticNode};
+use serde::{Deserialize, Serialize};
+
+/// Name of the custom section holding the manifest of a module
+pub const SECTION_NAME: &str = "__holium_manifest";
+
+/// Manifest entry of a transformation, as written by generated code
+pub struct Entry {
+    /// Name the transformation is exported under
+    pub name: &'static str,
+    /// Doc comments of the transformation
+    pub docs: &'static str,
+    /// Parameters of the transformation
+    pub parameters: &'static [Parameter],
+    /// Outputs of the transformation, unnamed outputs being named after their position
+    pub outputs: &'static [Parameter],
+    /// Key trees of the input payload and of the output of the transformation
+    pub key_trees: (StaticNode, StaticNode),
+}
+
+/// Named and typed input or output of a transformation, as written by generated code
+pub struct Parameter {
+    pub name: &'static str,
+    pub ty: &'static str,
+}
+
+impl Entry {
+    /// Length of the CBOR encoding of the entry
+    pub const fn encoded_len(&self) -> usize {
+        Writer::<0>::new().entry(self).len
+    }
+
+    /// CBOR encoding of the entry. `N` has to be the [`encoded_len`](Entry::encoded_len) of the
+    /// entry.
+    pub const fn encode<const N: usize>(&self) -> [u8; N] {
+        let writer = Writer::<N>::new().entry(self);
+        if writer.len != N {
+            panic!("manifest entry length does not match its encoding");
+        }
+        writer.bytes
+    }
+}
+
+/// Minimal CBOR encoder that can run in constant contexts. Bytes overflowing its buffer are only
+/// counted, which allows to compute the length of an encoding with an empty buffer.
+struct Writer<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Writer<N> {
+    const MAJOR_UNSIGNED: u8 = 0;
+    const MAJOR_TEXT: u8 = 3;
+    const MAJOR_ARRAY: u8 = 4;
+    const MAJOR_MAP: u8 = 5;
+    const NULL: u8 = 0xf6;
+
+    const fn new() -> Self {
+        Writer {
+            bytes: [0; N],
+            len: 0,
+        }
+    }
+
+    const fn byte(mut self, byte: u8) -> Self {
+        if self.len < N {
+            self.bytes[self.len] = byte;
+        }
+        self.len += 1;
+        self
+    }
+
+    /// Write the head of a data item, holding its major type and an argument
+    const fn head(self, major: u8, argument: u64) -> Self {
+        let (additional, size) = match argument {
+            0..=23 => (argument as u8, 0),
+            24..=0xff => (24, 1),
+            0x100..=0xffff => (25, 2),
+            0x1_0000..=0xffff_ffff => (26, 4),
+            _ => (27, 8),
+        };
+
+        let mut writer = self.byte(major << 5 | additional);
+        let mut i = size;
+        while i > 0 {
+            i -= 1;
+            writer = writer.byte((argument >> (8 * i)) as u8);
+        }
+        writer
+    }
+
+    const fn text(self, text: &str) -> Self {
+        let bytes = text.as_bytes();
+        let mut writer = self.head(Self::MAJOR_TEXT, bytes.len() as u64);
+        let mut i = 0;
+        while i < bytes.len() {
+            writer = writer.byte(bytes[i]);
+            i += 1;
+        }
+        writer
+    }
+
+    const fn parameters(self, parameters: &[Parameter]) -> Self {
+        let mut writer = self.head(Self::MAJOR_ARRAY, parameters.len() as u64);
+        let mut i = 0;
+        while i < parameters.len() {
+            writer = writer
+                .head(Self::MAJOR_MAP, 2)
+                .text("name")
+                .text(parameters[i].name)
+                .text("type")
+                .text(parameters[i].ty);
+            i += 1;
+        }
+        writer
+    }
+
+    /// Write a key tree the way serde serializes a [`KeyTree`]
+    const fn key_tree(self, node: &StaticNode) -> Self {
+        let entries = match node.type_name {
+            Some(_) => 4,
+            None => 3,
+        };
+        let mut writer = self.head(Self::MAJOR_MAP, entries).text("value");
+        writer = match node.value {
+            Some(value) => writer.text(value),
+            None => writer.byte(Self::NULL),
+        };
+
+        writer = writer
+            .text("children")
+            .head(Self::MAJOR_ARRAY, node.children.len() as u64);
+        let mut i = 0;
+        while i < node.children.len() {
+            writer = writer.key_tree(&node.children[i]);
+            i += 1;
+        }
+
+        writer = writer.text("shape");
+        writer = match node.shape {
+            key_tree::Shape::Plain => writer.text("Plain"),
+            key_tree::Shape::Sequence => writer.text("Sequence"),
+            key_tree::Shape::Array(len) => writer
+                .head(Self::MAJOR_MAP, 1)
+                .text("Array")
+                .head(Self::MAJOR_UNSIGNED, len as u64),
+            key_tree::Shape::Enum => writer.text("Enum"),
+            key_tree::Shape::Map => writer.text("Map"),
+            key_tree::Shape::Flattened => writer.text("Flattened"),
+            key_tree::Shape::Tuple => writer.text("Tuple"),
+            key_tree::Shape::Recursive(reference) => writer
+                .head(Self::MAJOR_MAP, 1)
+                .text("Recursive")
+                .text(reference.type_name),
+        };
+
+        match node.type_name {
+            Some(type_name) => writer.text("type_name").text(type_name),
+            None => writer,
+        }
+    }
+
+    const fn entry(self, entry: &Entry) -> Self {
+        self.head(Self::MAJOR_MAP, 5)
+            .text("name")
+            .text(entry.name)
+            .text("docs")
+            .text(entry.docs)
+            .text("parameters")
+            .parameters(entry.parameters)
+            .text("outputs")
+            .parameters(entry.outputs)
+            .text("key_trees")
+            .head(Self::MAJOR_ARRAY, 2)
+            .key_tree(&entry.key_trees.0)
+            .key_tree(&entry.key_trees.1)
+    }
+}
+
+/// Manifest entry of a transformation, as read from a module
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transformation {
+    pub name: String,
+    pub docs: String,
+    pub parameters: Vec<TypedName>,
+    pub outputs: Vec<TypedName>,
+    /// Key trees of the input payload and of the output of the transformation
+    pub key_trees: (KeyTree, KeyTree),
+}
+
+/// Named and typed input or output of a transformation, as read from a module
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypedName {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// Read the entries of a manifest custom section
+pub fn parse_section(section: &[u8]) -> Result<Vec<Transformation>, serde_cbor::Error> {
+    serde_cbor::Deserializer::from_slice(section)
+        .into_iter::<Transformation>()
+        .collect()
+}
+
+/// Key tree as read from a module, owning the keys held by a [`key_tree::Node`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyTree {
+    pub value: Option<String>,
+    pub children: Vec<KeyTree>,
+    pub shape: KeyShape,
+    /// Name of the recursive type described by the node, referenced by the
+    /// [`KeyShape::Recursive`] nodes under it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_name: Option<String>,
+}
+
+/// Shape of the data described by a key tree, as read from a module. Recursive shapes only hold
+/// the name of the type nested in itself, whose key tree is the closest node above them labeled
+/// with this name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyShape {
+    Plain,
+    Sequence,
+    Array(usize),
+    Enum,
+    Map,
+    Flattened,
+    Tuple,
+    Recursive(String),
+}
+
+impl From<&StaticNode> for KeyTree {
+    fn from(node: &StaticNode) -> Self {
+        KeyTree {
+            value: node.value.map(String::from),
+            children: node.children.iter().map(KeyTree::from).collect(),
+            shape: match node.shape {
+                key_tree::Shape::Plain => KeyShape::Plain,
+                key_tree::Shape::Sequence => KeyShape::Sequence,
+                key_tree::Shape::Array(len) => KeyShape::Array(len),
+                key_tree::Shape::Enum => KeyShape::Enum,
+                key_tree::Shape::Map => KeyShape::Map,
+                key_tree::Shape::Flattened => KeyShape::Flattened,
+                key_tree::Shape::Tuple => KeyShape::Tuple,
+                key_tree::Shape::Recursive(reference) => {
+                    KeyShape::Recursive(String::from(reference.type_name))
+                }
+            },
+            type_name: node.type_name.map(String::from),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::key_tree::GenerateNode;
+    use crate::testing::fixtures::Chain;
+
+    const SUM: Entry = Entry {
+        name: "sum",
+        docs: "Sum values",
+        parameters: &[Parameter {
+            name: "values",
+            ty: "Vec<u32>",
+        }],
+        outputs: &[Parameter {
+            name: "0",
+            ty: "u32",
+        }],
+        key_trees: (
+            StaticNode {
+                children: &[StaticNode {
+                    value: Some("values"),
+                    ..Vec::<u32>::KEY_NODE
+                }],
+                ..StaticNode::DEFAULT
+            },
+            <(u32,)>::KEY_NODE,
+        ),
+    };
+
+    const LONG: Entry = Entry {
+        name: "long",
+        docs: include_str!("manifest.rs"),
+        parameters: &[],
+        outputs: &[],
+        key_trees: (
+            StaticNode::DEFAULT,
+            <(Chain, std::collections::HashMap<String, ()>, [u8; 300])>::KEY_NODE,
+        ),
+    };
+
+    static SUM_BYTES: [u8; SUM.encoded_len()] = SUM.encode();
+    static LONG_BYTES: [u8; LONG.encoded_len()] = LONG.encode();
+
+    #[test]
+    fn can_parse_section_of_entries() {
+        let section = [&SUM_BYTES[..], &LONG_BYTES[..]].concat();
+
+        let transformations = parse_section(&section).unwrap();
+
+        assert_eq!(
+            transformations,
+            vec![
+                Transformation {
+                    name: String::from("sum"),
+                    docs: String::from("Sum values"),
+                    parameters: vec![TypedName {
+                        name: String::from("values"),
+                        ty: String::from("Vec<u32>"),
+                    }],
+                    outputs: vec![TypedName {
+                        name: String::from("0"),
+                        ty: String::from("u32"),
+                    }],
+                    key_trees: (
+                        KeyTree::from(&SUM.key_trees.0),
+                        KeyTree::from(&SUM.key_trees.1)
+                    ),
+                },
+                Transformation {
+                    name: String::from("long"),
+                    docs: String::from(include_str!("manifest.rs")),
+                    parameters: vec![],
+                    outputs: vec![],
+                    key_trees: (
+                        KeyTree::from(&LONG.key_trees.0),
+                        KeyTree::from(&LONG.key_trees.1)
+                    ),
+                },
+            ]
+        );
+        assert_eq!(
+            serde_cbor::to_vec(&transformations[0]).unwrap(),
+            SUM_BYTES.to_vec()
+        );
+        assert_eq!(
+            serde_cbor::to_vec(&transformations[1]).unwrap(),
+            LONG_BYTES.to_vec()
+        );
+    }
+
+    #[test]
+    fn recursive_key_trees_are_labeled() {
+        let (_, output) = &parse_section(&LONG_BYTES).unwrap()[0].key_trees;
+
+        let chain = &output.children[0];
+        assert_eq!(chain.type_name.as_deref(), Some("fixtures::Chain"));
+        assert_eq!(
+            chain.children[1].shape,
+            KeyShape::Recursive(String::from("fixtures::Chain"))
+        );
+        assert_eq!(chain.children[1].type_name, None);
+        assert_eq!(output.children[1].shape, KeyShape::Map);
+        assert_eq!(output.children[2].shape, KeyShape::Array(300));
+    }
+
+    #[test]
+    fn can_write_heads_of_any_argument() {
+        for argument in [
+            0,
+            23,
+            24,
+            0xff,
+            0x100,
+            0xffff,
+            0x1_0000,
+            0xffff_ffff,
+            0x1_0000_0000,
+            u64::MAX,
+        ] {
+            let writer = Writer::<9>::new().head(Writer::<9>::MAJOR_UNSIGNED, argument);
+
+            assert_eq!(
+                writer.bytes[..writer.len].to_vec(),
+                serde_cbor::to_vec(&argument).unwrap()
+            );
+        }
+    }
+}
@@ -1,28 +1,168 @@
-use std::alloc::{alloc, Layout};
-use std::mem;
+//! Memory related helpers shared between a guest module and its host.
+//!
+//! # Ownership of buffers
+//!
+//! Every buffer exchanged between the host and a guest module is a boxed `[u8]` living in the
+//! guest linear memory, and is always released through [`__hbindgen_mem_free`] with the exact
+//! length it was created with:
+//! 1. the host allocates an input buffer with [`__hbindgen_mem_alloc`] and writes its payload in
+//!    it ;
+//! 2. the host allocates the area the [`Output`] of the transformation is written to with
+//!    [`__hbindgen_output_alloc`], which unlike buffers is aligned as an [`Output`] ;
+//! 3. the host calls a transformation with the pointer of the output area, and the pointer and
+//!    length of the input buffer. The input buffer is only borrowed by the guest, it is still owned
+//!    by the host after the call ;
+//! 4. the guest leaks the buffer of the returned [`Output`], which is then owned by the host ;
+//! 5. once done reading them, the host releases both the input and the output buffers with
+//!    [`__hbindgen_mem_free`], and the output area with [`__hbindgen_output_free`].
+//!
+//! Generated transformation shims return an [`Output`] to the host. Its `status` word tells how
+//! the bytes pointed to by its `slice` should be read:
+//! - [`Status::Success`]: the slice holds the CBOR-encoded output data tree ;
+//! - [`Status::Error`]: the slice holds a CBOR-encoded [`Error`], which includes the path of the
+//!   failing field whenever it is known ;
+//! - [`Status::Failure`]: the transformation returned a `Result::Err`, the slice holds a
+//!   CBOR-encoded [`Failure`].
 
+use crate::internal::cbor;
+use crate::internal::error::{Error, Failure};
+
+/// Pointer and length of a buffer living in the guest memory. Both words are 32 bits wide in a
+/// wasm32 module, and pointer-sized when transformations are called natively.
+#[repr(C)]
 pub struct Slice {
-    pub ptr: u32,
-    pub len: u32,
+    pub ptr: usize,
+    pub len: usize,
+}
+
+/// Status word telling the host how to read the slice returned by a transformation
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Success = 0,
+    Error = 1,
+    Failure = 2,
+}
+
+/// Value returned to the host by generated transformation shims
+#[repr(C)]
+pub struct Output {
+    pub status: Status,
+    pub slice: Slice,
+}
+
+/// Size of an [`Output`] in a wasm32 module: a status word followed by the pointer and length of a
+/// buffer
+pub const WASM32_OUTPUT_SIZE: usize = 12;
+
+#[cfg(target_arch = "wasm32")]
+const _: () = assert!(std::mem::size_of::<Output>() == WASM32_OUTPUT_SIZE);
+
+impl Output {
+    /// Output holding the CBOR-encoded output data tree of a successful call
+    pub fn success(bytes: Vec<u8>) -> Self {
+        Self::new(Status::Success, bytes)
+    }
+
+    /// Output holding an error that prevented the transformation from being run
+    pub fn error(error: Error) -> Self {
+        // Encoding our own error type to CBOR is infallible
+        Self::new(Status::Error, cbor::to_vec(&error).unwrap_or_default())
+    }
+
+    /// Output holding the failure returned by the transformation itself
+    pub fn failure(failure: Failure) -> Self {
+        Self::new(Status::Failure, cbor::to_vec(&failure).unwrap_or_default())
+    }
+
+    fn new(status: Status, bytes: Vec<u8>) -> Self {
+        // Output buffer is leaked on purpose, it is up to the host to release it
+        let len = bytes.len();
+        let ptr = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+
+        Output {
+            status,
+            slice: Slice {
+                ptr: ptr as usize,
+                len,
+            },
+        }
+    }
 }
 
 /// Allows to allocate memory space for a given size. Used when transmitting data from the host to
-/// a guest module.
+/// a guest module. The allocated buffer has to be released with [`__hbindgen_mem_free`].
 #[no_mangle]
 pub extern "C" fn __hbindgen_mem_alloc(size: usize) -> *mut u8 {
-    let align = mem::align_of::<usize>();
-    if let Ok(layout) = Layout::from_size_align(size, align) {
-        unsafe {
-            if layout.size() > 0 {
-                let ptr = alloc(layout);
-                if !ptr.is_null() {
-                    return ptr;
-                }
-            } else {
-                return align as *mut u8;
+    Box::into_raw(vec![0u8; size].into_boxed_slice()) as *mut u8
+}
+
+/// Allows to release a buffer previously allocated by [`__hbindgen_mem_alloc`] or returned in the
+/// [`Output`] of a transformation.
+///
+/// # Safety
+///
+/// `ptr` and `len` must be the exact pointer and length of a buffer owned by the host that has not
+/// been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn __hbindgen_mem_free(ptr: *mut u8, len: usize) {
+    drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)));
+}
+
+/// Allows to allocate the area a transformation writes its [`Output`] to, aligned as an
+/// [`Output`]. The area has to be released with [`__hbindgen_output_free`].
+#[no_mangle]
+pub extern "C" fn __hbindgen_output_alloc() -> *mut Output {
+    Box::into_raw(Box::new(Output {
+        status: Status::Success,
+        slice: Slice { ptr: 0, len: 0 },
+    }))
+}
+
+/// Allows to release an area previously allocated by [`__hbindgen_output_alloc`]. The buffer the
+/// [`Output`] points to is left untouched, and is released separately with
+/// [`__hbindgen_mem_free`].
+///
+/// # Safety
+///
+/// `ptr` must be the exact pointer of an area owned by the host that has not been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn __hbindgen_output_free(ptr: *mut Output) {
+    drop(Box::from_raw(ptr));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_allocate_and_free() {
+        for size in [0usize, 1, 1024] {
+            let ptr = __hbindgen_mem_alloc(size);
+            assert!(!ptr.is_null());
+            unsafe {
+                let buffer = std::slice::from_raw_parts_mut(ptr, size);
+                buffer.iter_mut().for_each(|byte| *byte = 42);
+                __hbindgen_mem_free(ptr, size);
             }
         }
     }
 
-    std::process::abort();
+    #[test]
+    fn can_allocate_and_free_outputs() {
+        let ptr = __hbindgen_output_alloc();
+        assert_eq!(ptr as usize % std::mem::align_of::<Output>(), 0);
+        unsafe {
+            ptr.write(Output::success(vec![42]));
+            let output = ptr.read();
+            __hbindgen_output_free(ptr);
+            __hbindgen_mem_free(output.slice.ptr as *mut u8, output.slice.len);
+        }
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn has_wasm32_output_size() {
+        assert_eq!(std::mem::size_of::<Output>(), WASM32_OUTPUT_SIZE);
+    }
 }
@@ -4,8 +4,13 @@
 pub use serde;
 pub use serde_cbor;
 
+pub mod array;
+pub mod cbor;
 pub mod data_tree;
+pub mod error;
 pub mod key_tree;
+pub mod manifest;
+pub mod panic;
 pub mod version_embedder;
 
 #[allow(dead_code)]
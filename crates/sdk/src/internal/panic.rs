@@ -0,0 +1,197 @@
+//! Panics raised in transformations are recorded so that they can be reported to the host as an
+//! [`Error::Panic`] instead of a bare trap.
+//!
+//! On targets supporting unwinding, a panic is caught and handed to the host through the error
+//! channel of the transformation [`Output`].
+//!
+//! Targets that abort on panic, such as `wasm32-unknown-unknown` by default, cannot catch
+//! anything: [`catch_unwind`] never returns an error and the instance traps. The panic hook still
+//! runs before the trap, so panics are reported by following a trap-then-query protocol:
+//! 1. the panic hook records the message and location of the panic in the guest memory, then the
+//!    instance traps ;
+//! 2. the host keeps the trapped instance and calls [`__hbindgen_last_panic`] on it. The returned
+//!    [`Output`] holds the CBOR-encoded [`Error::Panic`] under an error status, or an empty slice
+//!    under a success status if the trap was not caused by a panic ;
+//! 3. the host releases the returned buffer as any other output, and then discards the instance,
+//!    whose state cannot be trusted anymore to run transformations.
+//!
+//! The record is cleared when a transformation starts and once it has been fetched, so that a
+//! panic is never reported twice or for another call.
+
+use crate::internal::error::Error;
+use crate::internal::memory::Output;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+static INSTALL_HOOK: Once = Once::new();
+
+thread_local! {
+    /// Name of the transformation currently running
+    static TRANSFORMATION: RefCell<Option<&'static str>> = const { RefCell::new(None) };
+    /// Record of the last panic that occurred in a transformation
+    static LAST_PANIC: RefCell<Option<Error>> = const { RefCell::new(None) };
+}
+
+/// Install, once, a panic hook recording panic messages and locations before handing over to the
+/// previously installed hook
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let transformation = TRANSFORMATION.with(|t| *t.borrow());
+            if let Some(transformation) = transformation {
+                let payload = info.payload();
+                let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                    message.to_string()
+                } else if let Some(message) = payload.downcast_ref::<String>() {
+                    message.clone()
+                } else {
+                    String::from("Box<dyn Any>")
+                };
+                let location = match info.location() {
+                    Some(location) => format!("{}:{}", location.file(), location.line()),
+                    None => String::from("<unknown>"),
+                };
+
+                LAST_PANIC.with(|last_panic| {
+                    *last_panic.borrow_mut() = Some(Error::Panic {
+                        transformation: transformation.to_string(),
+                        message,
+                        location,
+                    })
+                });
+            }
+            previous_hook(info);
+        }));
+    });
+}
+
+/// Run a transformation, turning any panic it raises into an [`Error::Panic`]
+pub fn catch_unwind<F, R>(transformation: &'static str, f: F) -> Result<R, Error>
+where
+    F: FnOnce() -> R,
+{
+    install_hook();
+    TRANSFORMATION.with(|t| *t.borrow_mut() = Some(transformation));
+    LAST_PANIC.with(|last_panic| *last_panic.borrow_mut() = None);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+
+    TRANSFORMATION.with(|t| *t.borrow_mut() = None);
+    result.map_err(|_| {
+        LAST_PANIC
+            .with(|last_panic| last_panic.borrow().clone())
+            .unwrap_or_else(|| Error::Panic {
+                transformation: transformation.to_string(),
+                message: String::from("unknown panic"),
+                location: String::from("<unknown>"),
+            })
+    })
+}
+
+/// Allows the host to fetch, once, the record of the last panic that occurred in a transformation,
+/// mainly after a trap on targets that abort on panic. The returned [`Output`] has a success status
+/// and an empty slice if no panic was recorded.
+#[no_mangle]
+pub extern "C" fn __hbindgen_last_panic() -> Output {
+    match LAST_PANIC.with(|last_panic| last_panic.borrow_mut().take()) {
+        Some(error) => Output::error(error),
+        None => Output::success(vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::memory::Status;
+
+    #[test]
+    fn can_catch_panic() {
+        let line = line!() + 2;
+        let result: Result<(), Error> = catch_unwind("main", || {
+            panic!("index out of bounds");
+        });
+
+        let error = result.unwrap_err();
+        assert_eq!(
+            error,
+            Error::Panic {
+                transformation: String::from("main"),
+                message: String::from("index out of bounds"),
+                location: format!("{}:{}", file!(), line),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "transformation `main` panicked at {}:{}: index out of bounds",
+                file!(),
+                line
+            )
+        );
+    }
+
+    #[test]
+    fn can_query_panic_after_abort() {
+        const CHILD_ENV: &str = "HOLIUM_PANIC_ABORT_CHILD";
+        const RECORD_PREFIX: &str = "last panic: ";
+
+        if std::env::var_os(CHILD_ENV).is_some() {
+            // Behave as a target aborting on panic, querying the record as a host would once the
+            // instance trapped
+            panic::set_hook(Box::new(|_| {
+                let output = __hbindgen_last_panic();
+                let bytes = unsafe {
+                    Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                        output.slice.ptr as *mut u8,
+                        output.slice.len,
+                    ))
+                };
+                let error: Error = serde_cbor::from_slice(&bytes).unwrap();
+                eprintln!("{}{}", RECORD_PREFIX, error);
+                std::process::abort();
+            }));
+            let _: Result<(), Error> = catch_unwind("main", || panic!("index out of bounds"));
+            unreachable!("panic was not aborted");
+        }
+
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "internal::panic::tests::can_query_panic_after_abort",
+                "--nocapture",
+            ])
+            .env(CHILD_ENV, "1")
+            .output()
+            .unwrap();
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success());
+        assert!(
+            stderr.contains(&format!(
+                "{}transformation `main` panicked at {}:",
+                RECORD_PREFIX,
+                file!()
+            )),
+            "{}",
+            stderr
+        );
+        assert!(stderr.contains(": index out of bounds"), "{}", stderr);
+    }
+
+    #[test]
+    fn last_panic_is_fetched_once() {
+        let _ = catch_unwind("main", || panic!("index out of bounds"));
+
+        assert!(matches!(__hbindgen_last_panic().status, Status::Error));
+        let output = __hbindgen_last_panic();
+        assert!(matches!(output.status, Status::Success));
+        assert_eq!(output.slice.len, 0);
+    }
+
+    #[test]
+    fn can_run_without_panic() {
+        assert_eq!(catch_unwind("main", || 42).unwrap(), 42);
+    }
+}
@@ -6,3 +6,6 @@ pub use holium_macro::holium_bindgen;
 pub use internal::key_tree::GenerateNode;
 
 pub mod internal;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod types;
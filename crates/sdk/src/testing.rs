@@ -0,0 +1,167 @@
+//! Harness calling transformations natively, through the same ABI as a Holium host. It allows to
+//! write plain `#[test]`s for functions tagged with `#[holium_bindgen]`.
+//!
+//! The harness is left out of transformation modules, and only built with the `testing` feature,
+//! to be enabled from `[dev-dependencies]`.
+//!
+//! A transformation is reached through the two functions generated for it, named after the name
+//! it is exported under:
+//! - `__holium_bindgen_generated_<name>`, the shim called by the host ;
+//! - `__holium_bindgen_key_trees_<name>`, returning the key trees of its inputs and
+//!   outputs.
+//!
+//! Names that are not Rust identifiers are mangled, each character other than an ASCII
+//! alphanumeric being replaced by its hexadecimal code point between underscores, after a leading
+//! `0`: a transformation exported as `sum-of-values` is reached through
+//! `__holium_bindgen_generated_0sum_2d_of_2d_values`.
+//!
+//! ```ignore
+//! let sum = Transformation::new(
+//!     __holium_bindgen_generated_sum,
+//!     __holium_bindgen_key_trees_sum,
+//! );
+//! let (output,): (u32,) = sum.call(&(vec![1u32, 2, 3],))?.deserialize()?;
+//! ```
+
+use crate::internal::data_tree;
+use crate::internal::error::{Error, Failure, ROOT_PATH};
+use crate::internal::key_tree;
+use crate::internal::memory::{self, Status};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_cbor::Value as CborValue;
+
+#[doc(hidden)]
+pub mod fixtures;
+
+/// Shim generated for a transformation, called by the host
+pub type Shim = extern "C" fn(*mut u8, usize) -> memory::Output;
+
+/// Function generated for a transformation, returning the key trees of its inputs and outputs, as
+/// embedded in the manifest of the module
+pub type KeyTrees = fn() -> &'static (key_tree::StaticNode, key_tree::StaticNode);
+
+/// Reason why a call to a transformation did not succeed
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum CallError {
+    /// Input payload could not be built from the given values
+    #[error("failed to build input payload: {0}")]
+    Payload(String),
+    /// Transformation could not be run, as reported by its shim
+    #[error(transparent)]
+    Error(#[from] Error),
+    /// Transformation returned a `Result::Err`
+    #[error("transformation failed: {}", .0.message)]
+    Failure(Failure),
+    /// Bytes returned by the shim could not be decoded
+    #[error("failed to decode returned bytes: {0}")]
+    Decode(String),
+}
+
+/// Transformation exported with `#[holium_bindgen]`
+#[derive(Clone, Copy)]
+pub struct Transformation {
+    shim: Shim,
+    key_trees: KeyTrees,
+}
+
+impl Transformation {
+    pub fn new(shim: Shim, key_trees: KeyTrees) -> Self {
+        Transformation { shim, key_trees }
+    }
+
+    /// Call the transformation with the given inputs, usually a tuple holding one value per
+    /// parameter of the transformation
+    pub fn call<I>(&self, inputs: &I) -> Result<Output, CallError>
+    where
+        I: Serialize + ?Sized,
+    {
+        let payload = data_tree::Node::from_serializable(inputs)
+            .map_err(|e| CallError::Payload(e.to_string()))?;
+        self.call_payload(&payload)
+    }
+
+    /// Call the transformation with the given input data tree
+    pub fn call_payload(&self, payload: &data_tree::Node) -> Result<Output, CallError> {
+        let payload_u8 = payload
+            .encode()
+            .map_err(|e| CallError::Payload(e.to_string()))?;
+        let (status, bytes) = self.call_bytes(&payload_u8);
+
+        match status {
+            Status::Success => {
+                let data_tree: data_tree::Node =
+                    serde_cbor::from_slice(&bytes).map_err(|e| CallError::Decode(e.to_string()))?;
+                let (_, key_tree) = self.key_trees();
+                Ok(Output {
+                    data_tree,
+                    key_tree,
+                })
+            }
+            Status::Error => Err(CallError::Error(
+                serde_cbor::from_slice(&bytes).map_err(|e| CallError::Decode(e.to_string()))?,
+            )),
+            Status::Failure => Err(CallError::Failure(
+                serde_cbor::from_slice(&bytes).map_err(|e| CallError::Decode(e.to_string()))?,
+            )),
+        }
+    }
+
+    /// Call the shim with raw payload bytes, handling buffers the way a host does. Returns the
+    /// status and the bytes of the output of the shim.
+    pub fn call_bytes(&self, payload: &[u8]) -> (Status, Vec<u8>) {
+        let input_ptr = memory::__hbindgen_mem_alloc(payload.len());
+        // Input buffer has just been allocated with the length of the payload
+        let input = unsafe { std::slice::from_raw_parts_mut(input_ptr, payload.len()) };
+        input.copy_from_slice(payload);
+
+        let output = (self.shim)(input_ptr, payload.len());
+
+        let bytes =
+            unsafe { std::slice::from_raw_parts(output.slice.ptr as *const u8, output.slice.len) }
+                .to_vec();
+        // Both buffers are owned by the host once the shim returned
+        unsafe {
+            memory::__hbindgen_mem_free(output.slice.ptr as *mut u8, output.slice.len);
+            memory::__hbindgen_mem_free(input_ptr, payload.len());
+        }
+
+        (output.status, bytes)
+    }
+
+    /// Key trees of the inputs and outputs of the transformation
+    pub fn key_trees(&self) -> (key_tree::Node, key_tree::Node) {
+        let (input, output) = (self.key_trees)();
+        (key_tree::Node::from(input), key_tree::Node::from(output))
+    }
+}
+
+/// Output of a successful call to a transformation
+#[derive(Debug)]
+pub struct Output {
+    data_tree: data_tree::Node,
+    key_tree: key_tree::Node,
+}
+
+impl Output {
+    /// Output data tree, as returned by the transformation
+    pub fn data_tree(&self) -> &data_tree::Node {
+        &self.data_tree
+    }
+
+    /// Output with keys assigned from the output key tree of the transformation. Named outputs are
+    /// a map from their names to their values, while an unnamed output is an array holding the
+    /// returned value.
+    pub fn value(&self) -> Result<CborValue, Error> {
+        self.data_tree.assign_keys(&self.key_tree)
+    }
+
+    /// Deserialize the output with its keys assigned. Named outputs can be read as a structure
+    /// with one field per output, an unnamed output as a tuple of one element.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        serde_cbor::value::from_value(self.value()?).map_err(|e| Error::TypeMismatch {
+            path: String::from(ROOT_PATH),
+            message: e.to_string(),
+        })
+    }
+}
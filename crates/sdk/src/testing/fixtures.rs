@@ -0,0 +1,130 @@
+//! Fixtures shared by the tests of the Holium crates: a recursive type, and a module following the
+//! ABI of the SDK written in the wasm text format, to be parsed with the `wat` crate.
+
+use crate::internal::error::Error;
+use crate::internal::key_tree::{GenerateNode, Recursion, Recursive, StaticNode};
+use crate::internal::manifest::{Entry, Parameter};
+use crate::internal::memory::WASM32_OUTPUT_SIZE;
+
+/// Linked list of values, whose key tree refers to itself
+pub struct Chain;
+
+impl GenerateNode for Chain {
+    const KEY_NODE: StaticNode = StaticNode {
+        children: &[
+            StaticNode {
+                value: Some("value"),
+                ..u32::KEY_NODE
+            },
+            StaticNode {
+                value: Some("next"),
+                ..<Option<Box<Recursion<Chain>>>>::KEY_NODE
+            },
+        ],
+        type_name: Some(Chain::TYPE_NAME),
+        ..StaticNode::DEFAULT
+    };
+}
+
+impl Recursive for Chain {
+    const TYPE_NAME: &'static str = "fixtures::Chain";
+}
+
+/// Manifest entry of the `echo` transformation of [`module_wat`], returning its input
+pub const ECHO: Entry = Entry {
+    name: "echo",
+    docs: "Return its input",
+    parameters: &[Parameter {
+        name: "chain",
+        ty: "Chain",
+    }],
+    outputs: &[Parameter {
+        name: "0",
+        ty: "Chain",
+    }],
+    key_trees: (
+        StaticNode {
+            children: &[StaticNode {
+                value: Some("chain"),
+                ..Chain::KEY_NODE
+            }],
+            ..StaticNode::DEFAULT
+        },
+        <(Chain,)>::KEY_NODE,
+    ),
+};
+
+static MANIFEST: [u8; ECHO.encoded_len()] = ECHO.encode();
+
+/// Escape bytes to be written in a string of the wasm text format
+fn escape(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("\\{:02x}", byte)).collect()
+}
+
+/// Module built with version `1.0.0` of the SDK, whose manifest only lists [`ECHO`]. It has a bump
+/// allocator, aligning output areas, and transformations returning a copy of their input, an error, running forever,
+/// growing their memory, panicking or trapping.
+pub fn module_wat() -> String {
+    let error = serde_cbor::to_vec(&Error::PayloadDecode(String::from("bad payload"))).unwrap();
+    let panic = serde_cbor::to_vec(&Error::Panic {
+        transformation: String::from("panic"),
+        message: String::from("index out of bounds"),
+        location: String::from("src/lib.rs:3"),
+    })
+    .unwrap();
+
+    format!(
+        r#"(module
+            (memory (export "memory") 1)
+            (global $next (mut i32) (i32.const 1024))
+            (global $panicked (mut i32) (i32.const 0))
+            (data (i32.const 0) "{error}")
+            (data (i32.const 512) "{panic}")
+            (func $alloc (export "__hbindgen_mem_alloc") (param $size i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $next))
+                (global.set $next (i32.add (global.get $next) (local.get $size)))
+                (local.get $ptr))
+            (func (export "__hbindgen_mem_free") (param i32 i32))
+            (func (export "__hbindgen_output_alloc") (result i32)
+                (global.set $next (i32.and (i32.add (global.get $next) (i32.const 3)) (i32.const -4)))
+                (call $alloc (i32.const {output_size})))
+            (func (export "__hbindgen_output_free") (param i32))
+            (func $output (param $ret i32) (param $status i32) (param $ptr i32) (param $len i32)
+                (local $copy i32)
+                (local.set $copy (call $alloc (local.get $len)))
+                (memory.copy (local.get $copy) (local.get $ptr) (local.get $len))
+                (i32.store (local.get $ret) (local.get $status))
+                (i32.store offset=4 (local.get $ret) (local.get $copy))
+                (i32.store offset=8 (local.get $ret) (local.get $len)))
+            (func (export "echo") (param $ret i32) (param $ptr i32) (param $len i32)
+                (call $output (local.get $ret) (i32.const 0) (local.get $ptr) (local.get $len)))
+            (func (export "error") (param $ret i32) (param i32 i32)
+                (call $output (local.get $ret) (i32.const 1) (i32.const 0) (i32.const {error_len})))
+            (func (export "spin") (param i32 i32 i32)
+                (loop $forever (br $forever)))
+            (func (export "grow") (param i32 i32 i32)
+                (drop (memory.grow (i32.const 64))))
+            (func (export "panic") (param i32 i32 i32)
+                (global.set $panicked (i32.const 1))
+                (unreachable))
+            (func (export "trap") (param i32 i32 i32)
+                (unreachable))
+            (func (export "__hbindgen_last_panic") (param $ret i32)
+                (if (global.get $panicked)
+                    (then
+                        (global.set $panicked (i32.const 0))
+                        (call $output (local.get $ret) (i32.const 1) (i32.const 512) (i32.const {panic_len})))
+                    (else
+                        (call $output (local.get $ret) (i32.const 0) (i32.const 0) (i32.const 0)))))
+            (@custom "__holium_sdk_version" "1.0.0")
+            (@custom "__holium_manifest" "{manifest}")
+        )"#,
+        error = escape(&error),
+        error_len = error.len(),
+        panic = escape(&panic),
+        panic_len = panic.len(),
+        manifest = escape(&MANIFEST),
+        output_size = WASM32_OUTPUT_SIZE,
+    )
+}
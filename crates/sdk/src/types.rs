@@ -0,0 +1,435 @@
+//! Types carrying a Cbor tag through data trees, so that the semantics of their values are kept
+//! when they are exchanged with the host.
+//!
+//! Each type serializes through a newtype structure named after
+//! [`TAG_NEWTYPE_PREFIX`](crate::internal::data_tree::TAG_NEWTYPE_PREFIX) and its tag, which data
+//! trees turn into a tagged node. Other serializers only see the untagged value, and
+//! deserialization accepts values whether they were tagged or not.
+
+use crate::internal::key_tree::{GenerateNode, StaticNode};
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Cbor tag of epoch-based date/time values
+pub const TIMESTAMP_TAG: u64 = 1;
+/// Cbor tag of unsigned bignums
+pub const BIG_UINT_TAG: u64 = 2;
+/// Cbor tag of extended time values, as defined by RFC 9581
+pub const EXTENDED_TIME_TAG: u64 = 1001;
+
+const TIMESTAMP_NEWTYPE: &str = "\0holium_tag:1";
+const BIG_UINT_NEWTYPE: &str = "\0holium_tag:2";
+const EXTENDED_TIME_NEWTYPE: &str = "\0holium_tag:1001";
+
+/// Key of the seconds since the Unix epoch in an extended time map
+const EXTENDED_TIME_SECONDS: i64 = 1;
+/// Key of the nanoseconds elapsed since the last whole second in an extended time map
+const EXTENDED_TIME_NANOSECONDS: i64 = -9;
+
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+/// Point in time, held as the number of seconds and nanoseconds elapsed since the Unix epoch.
+///
+/// Whole seconds are encoded as an integer under Cbor tag 1. Other points in time are encoded
+/// without losing precision under Cbor tag 1001, as a map holding the seconds under key `1` and
+/// the nanoseconds under key `-9`. Epoch-based floats are accepted as well when deserializing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    seconds: i64,
+    nanoseconds: u32,
+}
+
+impl Timestamp {
+    /// Timestamp from seconds since the Unix epoch, plus nanoseconds that may exceed a second
+    ///
+    /// # Panics
+    ///
+    /// Panics if the carry from the nanoseconds makes the seconds overflow.
+    pub fn new(seconds: i64, nanoseconds: u32) -> Self {
+        Timestamp::checked_new(seconds, nanoseconds).expect("overflow in Timestamp::new")
+    }
+
+    /// Timestamp from seconds since the Unix epoch, plus nanoseconds that may exceed a second, or
+    /// `None` if the carry from the nanoseconds makes the seconds overflow
+    pub fn checked_new(seconds: i64, nanoseconds: u32) -> Option<Self> {
+        Some(Timestamp {
+            seconds: seconds.checked_add(i64::from(nanoseconds / NANOS_PER_SECOND))?,
+            nanoseconds: nanoseconds % NANOS_PER_SECOND,
+        })
+    }
+
+    /// Whole seconds elapsed since the Unix epoch, rounded down
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Nanoseconds elapsed since the last whole second
+    pub fn subsec_nanos(&self) -> u32 {
+        self.nanoseconds
+    }
+
+    fn from_f64(seconds: f64) -> Option<Self> {
+        if !seconds.is_finite() || seconds.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        let whole = seconds.floor();
+        let nanoseconds = ((seconds - whole) * f64::from(NANOS_PER_SECOND)).round() as u32;
+        Timestamp::checked_new(whole as i64, nanoseconds)
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => Timestamp::new(elapsed.as_secs() as i64, elapsed.subsec_nanos()),
+            Err(e) => {
+                let remaining = e.duration();
+                match remaining.subsec_nanos() {
+                    0 => Timestamp::new(-(remaining.as_secs() as i64), 0),
+                    nanoseconds => Timestamp::new(
+                        -(remaining.as_secs() as i64) - 1,
+                        NANOS_PER_SECOND - nanoseconds,
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        let since_epoch = Duration::new(timestamp.seconds.unsigned_abs(), 0);
+        let time = match timestamp.seconds {
+            seconds if seconds >= 0 => UNIX_EPOCH + since_epoch,
+            _ => UNIX_EPOCH - since_epoch,
+        };
+        time + Duration::from_nanos(u64::from(timestamp.nanoseconds))
+    }
+}
+
+/// Extended time map of a timestamp with a fraction of a second
+struct ExtendedTime<'a>(&'a Timestamp);
+
+impl Serialize for ExtendedTime<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry(&EXTENDED_TIME_SECONDS, &self.0.seconds)?;
+        map.serialize_entry(&EXTENDED_TIME_NANOSECONDS, &self.0.nanoseconds)?;
+        map.end()
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.nanoseconds {
+            0 => serializer.serialize_newtype_struct(TIMESTAMP_NEWTYPE, &self.seconds),
+            _ => serializer.serialize_newtype_struct(EXTENDED_TIME_NEWTYPE, &ExtendedTime(self)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimestampVisitor;
+
+        impl<'de> Visitor<'de> for TimestampVisitor {
+            type Value = Timestamp;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number of seconds since the Unix epoch or an extended time map")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Timestamp, E> {
+                Ok(Timestamp::new(v, 0))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Timestamp, E> {
+                i64::try_from(v)
+                    .map(|seconds| Timestamp::new(seconds, 0))
+                    .map_err(|_| E::custom("timestamp out of range"))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Timestamp, E> {
+                Timestamp::from_f64(v).ok_or_else(|| E::custom("timestamp out of range"))
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Timestamp, A::Error> {
+                let (mut seconds, mut nanoseconds) = (None, None);
+                while let Some(key) = map.next_key::<i64>()? {
+                    match key {
+                        EXTENDED_TIME_SECONDS => seconds = Some(map.next_value::<i64>()?),
+                        EXTENDED_TIME_NANOSECONDS => nanoseconds = Some(map.next_value::<u32>()?),
+                        key => {
+                            return Err(de::Error::custom(format!(
+                                "unsupported extended time key {}",
+                                key
+                            )))
+                        }
+                    }
+                }
+                let seconds = seconds.ok_or_else(|| de::Error::missing_field("1"))?;
+                match nanoseconds.unwrap_or(0) {
+                    nanoseconds if nanoseconds < NANOS_PER_SECOND => Ok(Timestamp {
+                        seconds,
+                        nanoseconds,
+                    }),
+                    _ => Err(de::Error::custom("nanoseconds exceed a second")),
+                }
+            }
+
+            fn visit_newtype_struct<D: Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<Timestamp, D::Error> {
+                deserializer.deserialize_any(self)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(TIMESTAMP_NEWTYPE, TimestampVisitor)
+    }
+}
+
+impl GenerateNode for Timestamp {
+    /// Extended time maps are held as maps with dynamic keys
+    const KEY_NODE: StaticNode = BTreeMap::<i64, u32>::KEY_NODE;
+}
+
+/// Arbitrarily large unsigned integer, held as its big-endian bytes under Cbor tag 2
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BigUint {
+    /// Big-endian bytes, without leading zeros
+    bytes: Vec<u8>,
+}
+
+impl BigUint {
+    /// Integer from its big-endian bytes, leading zeros being ignored
+    pub fn from_bytes_be(bytes: &[u8]) -> Self {
+        let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+        BigUint {
+            bytes: bytes[start..].to_vec(),
+        }
+    }
+
+    /// Big-endian bytes of the integer, without leading zeros. Zero has no bytes.
+    pub fn to_bytes_be(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Value of the integer, if it fits a `u128`
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.bytes.len() > 16 {
+            return None;
+        }
+        Some(self.bytes.iter().fold(0, |v, &b| v << 8 | u128::from(b)))
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(v: u64) -> Self {
+        BigUint::from_bytes_be(&v.to_be_bytes())
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(v: u128) -> Self {
+        BigUint::from_bytes_be(&v.to_be_bytes())
+    }
+}
+
+/// Bytes serialized as a byte string rather than a sequence
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl Serialize for BigUint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(BIG_UINT_NEWTYPE, &Bytes(&self.bytes))
+    }
+}
+
+impl<'de> Deserialize<'de> for BigUint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BigUintVisitor;
+
+        impl<'de> Visitor<'de> for BigUintVisitor {
+            type Value = BigUint;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("big-endian bytes of an unsigned integer")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<BigUint, E> {
+                Ok(BigUint::from_bytes_be(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<BigUint, E> {
+                Ok(BigUint::from(v))
+            }
+
+            fn visit_newtype_struct<D: Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<BigUint, D::Error> {
+                deserializer.deserialize_any(self)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(BIG_UINT_NEWTYPE, BigUintVisitor)
+    }
+}
+
+impl GenerateNode for BigUint {
+    const KEY_NODE: StaticNode = StaticNode::DEFAULT;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::cbor;
+    use crate::internal::data_tree;
+    use crate::internal::data_tree::TAG_NEWTYPE_PREFIX;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Transfer {
+        at: Timestamp,
+        amount: BigUint,
+    }
+
+    impl GenerateNode for Transfer {
+        const KEY_NODE: StaticNode = StaticNode {
+            children: &[
+                StaticNode {
+                    value: Some("at"),
+                    ..Timestamp::KEY_NODE
+                },
+                StaticNode {
+                    value: Some("amount"),
+                    ..BigUint::KEY_NODE
+                },
+            ],
+            ..StaticNode::DEFAULT
+        };
+    }
+
+    #[test]
+    fn newtype_names_carry_tags() {
+        for (name, tag) in [
+            (TIMESTAMP_NEWTYPE, TIMESTAMP_TAG),
+            (BIG_UINT_NEWTYPE, BIG_UINT_TAG),
+            (EXTENDED_TIME_NEWTYPE, EXTENDED_TIME_TAG),
+        ] {
+            assert_eq!(name, format!("{}{}", TAG_NEWTYPE_PREFIX, tag));
+        }
+    }
+
+    #[test]
+    fn tags_are_kept_through_data_trees() {
+        let transfer = Transfer {
+            at: Timestamp::new(1_700_000_000, 0),
+            amount: BigUint::from(u128::MAX),
+        };
+
+        let data_tree = data_tree::Node::from_serializable(&transfer).unwrap();
+        let cbor = data_tree.assign_keys(&Transfer::generate_node()).unwrap();
+
+        assert_eq!(
+            cbor::encode(&cbor).unwrap(),
+            [
+                &[0xa2, 0x62, b'a', b't', 0xc1, 0x1a, 0x65, 0x53, 0xf1, 0x00][..],
+                &[0x66, b'a', b'm', b'o', b'u', b'n', b't', 0xc2, 0x50],
+                &[0xff; 16],
+            ]
+            .concat()
+        );
+        assert_eq!(
+            data_tree::Node::strip_keys(cbor, &Transfer::generate_node()).unwrap(),
+            data_tree
+        );
+        assert_eq!(data_tree.deserialize::<Transfer>().unwrap(), transfer);
+    }
+
+    #[test]
+    fn timestamps_keep_their_nanoseconds() {
+        let timestamp = Timestamp::new(1_700_000_000, 1);
+
+        let data_tree = data_tree::Node::from_serializable(&(timestamp,)).unwrap();
+        let cbor = data_tree
+            .assign_keys(&<(Timestamp,)>::generate_node())
+            .unwrap();
+
+        assert_eq!(
+            cbor::encode(&cbor).unwrap(),
+            [0x81, 0xd9, 0x03, 0xe9, 0xa2, 0x01, 0x1a, 0x65, 0x53, 0xf1, 0x00, 0x28, 0x01]
+        );
+        assert_eq!(
+            data_tree::Node::strip_keys(cbor, &<(Timestamp,)>::generate_node()).unwrap(),
+            data_tree
+        );
+        assert_eq!(
+            data_tree.deserialize::<(Timestamp,)>().unwrap(),
+            (timestamp,)
+        );
+        let bytes = cbor::to_vec(&timestamp).unwrap();
+        assert_eq!(
+            serde_cbor::from_slice::<Timestamp>(&bytes).unwrap(),
+            timestamp
+        );
+    }
+
+    #[test]
+    fn timestamps_do_not_overflow() {
+        assert_eq!(Timestamp::checked_new(i64::MAX, NANOS_PER_SECOND), None);
+        assert_eq!(
+            Timestamp::checked_new(i64::MAX, NANOS_PER_SECOND - 1),
+            Some(Timestamp {
+                seconds: i64::MAX,
+                nanoseconds: NANOS_PER_SECOND - 1,
+            })
+        );
+    }
+
+    #[test]
+    fn can_deserialize_untagged_values() {
+        #[derive(Serialize)]
+        struct UntaggedTransfer {
+            at: f64,
+            amount: u64,
+        }
+
+        let bytes = cbor::to_vec(&UntaggedTransfer { at: 1.5, amount: 7 }).unwrap();
+        let transfer: Transfer = serde_cbor::from_slice(&bytes).unwrap();
+
+        assert_eq!(transfer.at, Timestamp::new(1, 500_000_000));
+        assert_eq!(transfer.amount.to_u128(), Some(7));
+    }
+
+    #[test]
+    fn can_convert_timestamps() {
+        let before_epoch = UNIX_EPOCH - Duration::from_millis(1500);
+
+        let timestamp = Timestamp::from(before_epoch);
+
+        assert_eq!(timestamp, Timestamp::new(-2, 500_000_000));
+        assert_eq!(SystemTime::from(timestamp), before_epoch);
+        assert_eq!(Timestamp::from_f64(-1.5), Some(timestamp));
+    }
+
+    #[test]
+    fn big_uints_drop_leading_zeros() {
+        let big_uint = BigUint::from_bytes_be(&[0, 0, 1, 0]);
+
+        assert_eq!(big_uint.to_bytes_be(), &[1, 0]);
+        assert_eq!(big_uint, BigUint::from(256u64));
+        assert_eq!(BigUint::from(0u64).to_bytes_be(), &[] as &[u8]);
+        assert_eq!(BigUint::from_bytes_be(&[1; 17]).to_u128(), None);
+    }
+}
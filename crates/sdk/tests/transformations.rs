@@ -0,0 +1,282 @@
+use holium_rs_sdk::internal::error::Error;
+use holium_rs_sdk::internal::manifest::{KeyShape, KeyTree};
+use holium_rs_sdk::internal::memory;
+use holium_rs_sdk::testing::{CallError, Transformation};
+use holium_rs_sdk::types::{Timestamp, TIMESTAMP_TAG};
+use holium_rs_sdk::*;
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value as CborValue;
+use std::collections::HashMap;
+
+#[holium_bindgen]
+pub struct Range {
+    pub low: u32,
+    pub high: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmptyRange {
+    pub low: u32,
+}
+
+impl std::fmt::Display for EmptyRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "range starting at {} is empty", self.low)
+    }
+}
+
+#[holium_bindgen]
+pub fn width(range: Range) -> Result<u32, EmptyRange> {
+    match range.low < range.high {
+        true => Ok(range.high - range.low),
+        false => Err(EmptyRange { low: range.low }),
+    }
+}
+
+#[holium_bindgen(outputs(sum, count))]
+pub fn stats(values: Vec<u32>, offset: u32) -> (u32, usize) {
+    (values.iter().sum::<u32>() + offset, values.len())
+}
+
+#[holium_bindgen]
+pub fn halve(value: u32) -> u32 {
+    assert_eq!(value % 2, 0, "cannot halve an odd number");
+    value / 2
+}
+
+#[holium_bindgen]
+pub fn next_day(at: Timestamp) -> Timestamp {
+    Timestamp::new(at.seconds() + 86_400, at.subsec_nanos())
+}
+
+#[holium_bindgen]
+#[derive(Debug, PartialEq)]
+pub struct Labeled {
+    pub id: u32,
+    #[serde(flatten)]
+    pub labels: HashMap<String, u32>,
+}
+
+#[holium_bindgen]
+pub fn relabel(labeled: Labeled, label: String) -> Labeled {
+    let mut labels = labeled.labels;
+    labels.insert(label, labeled.id);
+    Labeled {
+        id: labeled.id + 1,
+        labels,
+    }
+}
+
+#[holium_bindgen]
+#[derive(Debug, PartialEq)]
+pub struct Tree<T> {
+    pub value: T,
+    pub children: Vec<Tree<T>>,
+}
+
+#[holium_bindgen(instantiate(T = u32, name = "grow"))]
+pub fn grow<T: Clone>(mut tree: Tree<T>) -> Tree<T> {
+    tree.children.push(Tree {
+        value: tree.value.clone(),
+        children: vec![],
+    });
+    tree
+}
+
+#[holium_bindgen]
+pub struct Signed {
+    pub signatures: Vec<[u8; 64]>,
+    pub window: Option<[f64; 1024]>,
+}
+
+#[holium_bindgen]
+pub fn sign(signed: Signed, signature: Option<[u8; 64]>) -> Vec<[u8; 64]> {
+    let mut signatures = signed.signatures;
+    signatures.extend(signature);
+    signatures
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Stats {
+    sum: u32,
+    count: usize,
+}
+
+#[test]
+fn can_call_transformation() {
+    let width = Transformation::new(
+        __holium_bindgen_generated_width,
+        __holium_bindgen_key_trees_width,
+    );
+
+    let output = width.call(&(Range { low: 2, high: 5 },)).unwrap();
+
+    assert_eq!(output.deserialize::<(u32,)>().unwrap(), (3,));
+}
+
+#[test]
+fn can_read_named_outputs() {
+    let stats = Transformation::new(
+        __holium_bindgen_generated_stats,
+        __holium_bindgen_key_trees_stats,
+    );
+
+    let output = stats.call(&(vec![1u32, 2, 3], 4u32)).unwrap();
+
+    assert_eq!(
+        output.deserialize::<Stats>().unwrap(),
+        Stats { sum: 10, count: 3 }
+    );
+}
+
+#[test]
+fn can_report_failure() {
+    let width = Transformation::new(
+        __holium_bindgen_generated_width,
+        __holium_bindgen_key_trees_width,
+    );
+
+    match width.call(&(Range { low: 5, high: 2 },)) {
+        Err(CallError::Failure(failure)) => {
+            assert_eq!(failure.message, "range starting at 5 is empty");
+            assert!(failure.payload.is_some());
+        }
+        other => panic!("expected a failure, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn can_report_errors() {
+    let stats = Transformation::new(
+        __holium_bindgen_generated_stats,
+        __holium_bindgen_key_trees_stats,
+    );
+    let halve = Transformation::new(
+        __holium_bindgen_generated_halve,
+        __holium_bindgen_key_trees_halve,
+    );
+
+    assert!(matches!(
+        stats.call(&(vec![1u32], "4")),
+        Err(CallError::Error(Error::TypeMismatch { .. }))
+    ));
+    assert!(matches!(
+        halve.call(&(3u32,)),
+        Err(CallError::Error(Error::Panic { .. }))
+    ));
+    assert!(matches!(
+        halve.call_bytes(&[0xff]),
+        (memory::Status::Error, _)
+    ));
+}
+
+#[test]
+fn can_exchange_tagged_values() {
+    let next_day = Transformation::new(
+        __holium_bindgen_generated_next_day,
+        __holium_bindgen_key_trees_next_day,
+    );
+
+    let output = next_day.call(&(Timestamp::new(0, 0),)).unwrap();
+
+    assert_eq!(
+        output.value().unwrap(),
+        CborValue::Array(vec![CborValue::Tag(
+            TIMESTAMP_TAG,
+            Box::new(CborValue::Integer(86_400))
+        )])
+    );
+    assert_eq!(
+        output.deserialize::<(Timestamp,)>().unwrap(),
+        (Timestamp::new(86_400, 0),)
+    );
+
+    let output = next_day.call(&(Timestamp::new(1_700_000_000, 1),)).unwrap();
+
+    assert_eq!(
+        output.deserialize::<(Timestamp,)>().unwrap(),
+        (Timestamp::new(1_700_086_400, 1),)
+    );
+}
+
+#[test]
+fn can_exchange_flattened_maps() {
+    let relabel = Transformation::new(
+        __holium_bindgen_generated_relabel,
+        __holium_bindgen_key_trees_relabel,
+    );
+    let labeled = Labeled {
+        id: 1,
+        labels: vec![(String::from("red"), 7)].into_iter().collect(),
+    };
+
+    let output = relabel.call(&(labeled, "blue")).unwrap();
+
+    assert_eq!(
+        output.deserialize::<(Labeled,)>().unwrap(),
+        (Labeled {
+            id: 2,
+            labels: vec![(String::from("red"), 7), (String::from("blue"), 1)]
+                .into_iter()
+                .collect(),
+        },)
+    );
+}
+
+#[test]
+fn can_exchange_nested_long_arrays() {
+    let sign = Transformation::new(
+        __holium_bindgen_generated_sign,
+        __holium_bindgen_key_trees_sign,
+    );
+    let signed = Signed {
+        signatures: vec![[1; 64]],
+        window: Some([0.5; 1024]),
+    };
+
+    let output = sign.call(&(signed, Some(vec![2u8; 64]))).unwrap();
+
+    assert_eq!(
+        output.deserialize::<(Vec<Vec<u8>>,)>().unwrap(),
+        (vec![vec![1; 64], vec![2; 64]],)
+    );
+}
+
+#[test]
+fn can_exchange_recursive_types() {
+    let grow = Transformation::new(
+        __holium_bindgen_generated_grow,
+        __holium_bindgen_key_trees_grow,
+    );
+    let tree = Tree {
+        value: 1u32,
+        children: vec![Tree {
+            value: 2,
+            children: vec![],
+        }],
+    };
+
+    let output = grow.call(&(tree,)).unwrap();
+
+    assert_eq!(
+        output.deserialize::<(Tree<u32>,)>().unwrap().0.children,
+        vec![
+            Tree {
+                value: 2,
+                children: vec![],
+            },
+            Tree {
+                value: 1,
+                children: vec![],
+            },
+        ]
+    );
+
+    let (input, _) = __holium_bindgen_key_trees_grow();
+    let tree = KeyTree::from(&input.children[0]);
+    assert_eq!(tree.type_name.as_deref(), Some("transformations::Tree"));
+    assert_eq!(
+        tree.children[1].children[0].shape,
+        KeyShape::Recursive(String::from("transformations::Tree"))
+    );
+}